@@ -1,4 +1,4 @@
-use crate::config::AppConfig;
+use crate::config::{AppConfig, SavedSearch};
 use crate::services::config_service::ConfigService;
 use crate::state::{AppState, RecentState};
 use std::sync::Arc;
@@ -99,6 +99,25 @@ pub fn set_preview_chars(
     ConfigService::set_preview_chars(&app, state.inner(), preview_chars)
 }
 
+#[tauri::command]
+pub fn set_frecency(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    weight: u32,
+    half_life_hours: u32,
+) -> Result<(), String> {
+    ConfigService::set_frecency(&app, state.inner(), weight, half_life_hours)
+}
+
+#[tauri::command]
+pub fn set_watch_quiet_ms(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    quiet_ms: u32,
+) -> Result<(), String> {
+    ConfigService::set_watch_quiet_ms(&app, state.inner(), quiet_ms)
+}
+
 #[tauri::command]
 pub fn set_show_shortcuts_hint(
     app: AppHandle,
@@ -115,3 +134,47 @@ pub fn clear_recent(
 ) -> Result<RecentState, String> {
     ConfigService::clear_recent(&app, state.inner())
 }
+
+#[tauri::command]
+pub fn list_saved_searches(state: State<Arc<AppState>>) -> Vec<SavedSearch> {
+    ConfigService::list_saved_searches(state.inner())
+}
+
+#[tauri::command]
+pub fn create_saved_search(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    name: String,
+    query: String,
+    favorites_only: bool,
+    hotkey: Option<String>,
+    regex: Option<bool>,
+) -> Result<SavedSearch, String> {
+    ConfigService::create_saved_search(
+        &app,
+        state.inner(),
+        name,
+        query,
+        favorites_only,
+        hotkey,
+        regex.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub fn update_saved_search(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    search: SavedSearch,
+) -> Result<SavedSearch, String> {
+    ConfigService::update_saved_search(&app, state.inner(), search)
+}
+
+#[tauri::command]
+pub fn delete_saved_search(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    id: String,
+) -> Result<Vec<SavedSearch>, String> {
+    ConfigService::delete_saved_search(&app, state.inner(), id)
+}
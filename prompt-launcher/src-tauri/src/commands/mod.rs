@@ -1,14 +1,22 @@
 pub mod config;
 pub mod prompts;
+pub mod tags;
 pub mod window;
 
 pub use config::{
-    clear_recent, get_config, push_recent, set_append_clipboard, set_auto_paste, set_auto_start,
-    set_hotkey, set_preview_chars, set_recent_enabled, set_show_shortcuts_hint,
-    set_top_tags_limit, set_top_tags_scope, toggle_favorite,
+    clear_recent, create_saved_search, delete_saved_search, get_config, list_saved_searches,
+    push_recent, set_append_clipboard, set_auto_paste, set_auto_start, set_frecency, set_hotkey,
+    set_preview_chars, set_recent_enabled, set_show_shortcuts_hint, set_top_tags_limit,
+    set_top_tags_scope, set_watch_quiet_ms, toggle_favorite, update_saved_search,
 };
 pub use prompts::{
-    create_prompt_file, delete_prompt_files, list_prompts, open_prompt_path, search_prompts,
-    set_prompts_dir, update_prompt_tags,
+    complete_tags, create_prompt_file, delete_prompt_files, get_tag_stats, list_prompts,
+    open_prompt_path, run_saved_search, search_prompt_hits, search_prompts, search_prompts_page,
+    set_folder_tags, set_prompt_roots, set_prompts_dir, suggest_prompt_tags, update_prompt_tags,
+};
+pub use tags::{
+    delete_tag, delete_tag_info, get_tag_policy, get_tag_rules, list_tag_aliases, list_tag_info,
+    merge_tag_aliases, merge_tags, preview_tag_rule, remove_tag_alias, rename_tag, set_tag_alias,
+    set_tag_info, set_tag_policy, set_tag_rules,
 };
 pub use window::{capture_active_window, focus_last_window, frontend_ready};
@@ -1,5 +1,9 @@
-use crate::prompts::PromptEntry;
+use crate::config::PromptRoot;
+use crate::prompts::{PromptEntry, SearchHit, SearchPage};
 use crate::services::prompts_service::PromptsService;
+use crate::tag_complete::TagCompletion;
+use crate::tag_stats::TagStats;
+use crate::tag_suggest::TagSuggestion;
 use crate::state::AppState;
 use std::sync::Arc;
 use tauri::{AppHandle, State};
@@ -15,8 +19,77 @@ pub fn search_prompts(
     query: String,
     limit: usize,
     favorites_only: bool,
-) -> Vec<PromptEntry> {
-    PromptsService::search(state.inner(), &query, limit, favorites_only)
+    regex: Option<bool>,
+) -> Result<Vec<PromptEntry>, String> {
+    PromptsService::search(
+        state.inner(),
+        &query,
+        limit,
+        favorites_only,
+        regex.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub fn run_saved_search(
+    state: State<Arc<AppState>>,
+    id: String,
+    limit: usize,
+) -> Result<Vec<PromptEntry>, String> {
+    PromptsService::run_saved_search(state.inner(), &id, limit)
+}
+
+#[tauri::command]
+pub fn search_prompts_page(
+    state: State<Arc<AppState>>,
+    query: String,
+    offset: usize,
+    limit: usize,
+    favorites_only: bool,
+    regex: Option<bool>,
+) -> Result<SearchPage, String> {
+    PromptsService::search_page(
+        state.inner(),
+        &query,
+        offset,
+        limit,
+        favorites_only,
+        regex.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub fn search_prompt_hits(
+    state: State<Arc<AppState>>,
+    query: String,
+    limit: usize,
+    favorites_only: bool,
+    regex: Option<bool>,
+) -> Result<Vec<SearchHit>, String> {
+    PromptsService::search_hits(
+        state.inner(),
+        &query,
+        limit,
+        favorites_only,
+        regex.unwrap_or(false),
+    )
+}
+
+#[tauri::command]
+pub fn get_tag_stats(
+    state: State<Arc<AppState>>,
+    query: Option<String>,
+    favorites_only: Option<bool>,
+    regex: Option<bool>,
+    limit: usize,
+) -> Result<TagStats, String> {
+    PromptsService::tag_stats(
+        state.inner(),
+        query.as_deref().unwrap_or_default(),
+        favorites_only.unwrap_or(false),
+        regex.unwrap_or(false),
+        limit,
+    )
 }
 
 #[tauri::command]
@@ -28,12 +101,22 @@ pub fn set_prompts_dir(
     PromptsService::set_prompts_dir(&app, state.inner(), path)
 }
 
+#[tauri::command]
+pub fn set_prompt_roots(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    roots: Vec<PromptRoot>,
+) -> Result<Vec<PromptEntry>, String> {
+    PromptsService::set_prompt_roots(&app, state.inner(), roots)
+}
+
 #[tauri::command]
 pub fn create_prompt_file(
     state: State<Arc<AppState>>,
     name: String,
+    root: Option<String>,
 ) -> Result<String, String> {
-    PromptsService::create_prompt_file(state.inner(), name)
+    PromptsService::create_prompt_file(state.inner(), name, root)
 }
 
 #[tauri::command]
@@ -63,3 +146,30 @@ pub fn update_prompt_tags(
 ) -> Result<Vec<PromptEntry>, String> {
     PromptsService::update_prompt_tags(state.inner(), paths, add, remove)
 }
+
+#[tauri::command]
+pub fn complete_tags(
+    state: State<Arc<AppState>>,
+    input: String,
+    limit: usize,
+) -> Vec<TagCompletion> {
+    PromptsService::complete_tags(state.inner(), &input, limit)
+}
+
+#[tauri::command]
+pub fn suggest_prompt_tags(
+    state: State<Arc<AppState>>,
+    path: String,
+    limit: usize,
+) -> Result<Vec<TagSuggestion>, String> {
+    PromptsService::suggest_tags(state.inner(), &path, limit)
+}
+
+#[tauri::command]
+pub fn set_folder_tags(
+    state: State<Arc<AppState>>,
+    folder: String,
+    tags: Vec<String>,
+) -> Result<Vec<PromptEntry>, String> {
+    PromptsService::set_folder_tags(state.inner(), folder, tags)
+}
@@ -0,0 +1,128 @@
+use crate::prompts::PromptEntry;
+use crate::services::tags_service::{TagEditReport, TagRuleMatch, TagsService};
+use crate::state::AppState;
+use crate::tag_policy::TagPolicy;
+use crate::tag_rules::TagRule;
+use crate::tags_meta::TagInfo;
+use std::collections::HashMap;
+use std::sync::Arc;
+use tauri::{AppHandle, State};
+
+#[tauri::command]
+pub fn list_tag_aliases(state: State<Arc<AppState>>) -> HashMap<String, String> {
+    TagsService::list_aliases(state.inner())
+}
+
+#[tauri::command]
+pub fn set_tag_alias(
+    state: State<Arc<AppState>>,
+    alias: String,
+    canonical: String,
+) -> Result<Vec<PromptEntry>, String> {
+    TagsService::set_alias(state.inner(), alias, canonical)
+}
+
+#[tauri::command]
+pub fn remove_tag_alias(
+    state: State<Arc<AppState>>,
+    alias: String,
+) -> Result<Vec<PromptEntry>, String> {
+    TagsService::remove_alias(state.inner(), alias)
+}
+
+#[tauri::command]
+pub fn merge_tag_aliases(state: State<Arc<AppState>>) -> Result<Vec<PromptEntry>, String> {
+    TagsService::merge_aliases(state.inner())
+}
+
+#[tauri::command]
+pub fn get_tag_policy(state: State<Arc<AppState>>) -> TagPolicy {
+    TagsService::get_policy(state.inner())
+}
+
+#[tauri::command]
+pub fn set_tag_policy(
+    state: State<Arc<AppState>>,
+    policy: TagPolicy,
+) -> Result<Vec<PromptEntry>, String> {
+    TagsService::set_policy(state.inner(), policy)
+}
+
+#[tauri::command]
+pub fn list_tag_info(state: State<Arc<AppState>>) -> HashMap<String, TagInfo> {
+    TagsService::list_tag_info(state.inner())
+}
+
+#[tauri::command]
+pub fn set_tag_info(
+    state: State<Arc<AppState>>,
+    tag: String,
+    info: TagInfo,
+) -> Result<HashMap<String, TagInfo>, String> {
+    TagsService::set_tag_info(state.inner(), tag, info)
+}
+
+#[tauri::command]
+pub fn delete_tag_info(
+    state: State<Arc<AppState>>,
+    tag: String,
+) -> Result<HashMap<String, TagInfo>, String> {
+    TagsService::delete_tag_info(state.inner(), tag)
+}
+
+#[tauri::command]
+pub fn get_tag_rules(state: State<Arc<AppState>>, root: String) -> Result<Vec<TagRule>, String> {
+    TagsService::get_tag_rules(state.inner(), root)
+}
+
+#[tauri::command]
+pub fn set_tag_rules(
+    state: State<Arc<AppState>>,
+    root: String,
+    rules: Vec<TagRule>,
+) -> Result<Vec<PromptEntry>, String> {
+    TagsService::set_tag_rules(state.inner(), root, rules)
+}
+
+#[tauri::command]
+pub fn preview_tag_rule(
+    state: State<Arc<AppState>>,
+    rule: TagRule,
+) -> Result<Vec<TagRuleMatch>, String> {
+    TagsService::preview_tag_rule(state.inner(), rule)
+}
+
+#[tauri::command]
+pub fn rename_tag(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    from: String,
+    to: String,
+    rename_files: bool,
+    dry_run: bool,
+) -> Result<TagEditReport, String> {
+    TagsService::rename_tag(&app, state.inner(), from, to, rename_files, dry_run)
+}
+
+#[tauri::command]
+pub fn merge_tags(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    sources: Vec<String>,
+    target: String,
+    rename_files: bool,
+    dry_run: bool,
+) -> Result<TagEditReport, String> {
+    TagsService::merge_tags(&app, state.inner(), sources, target, rename_files, dry_run)
+}
+
+#[tauri::command]
+pub fn delete_tag(
+    app: AppHandle,
+    state: State<Arc<AppState>>,
+    tag: String,
+    rename_files: bool,
+    dry_run: bool,
+) -> Result<TagEditReport, String> {
+    TagsService::delete_tag(&app, state.inner(), tag, rename_files, dry_run)
+}
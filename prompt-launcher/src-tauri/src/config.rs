@@ -4,9 +4,13 @@ use std::fs;
 use std::path::PathBuf;
 use tauri::{path::BaseDirectory, AppHandle, Manager};
 
+use crate::index_cache::INDEX_CACHE_FILENAME;
+
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct AppConfig {
     pub prompts_dir: String,
+    #[serde(default)]
+    pub prompt_roots: Vec<PromptRoot>,
     pub auto_paste: bool,
     #[serde(default)]
     pub append_clipboard: bool,
@@ -29,12 +33,82 @@ pub struct AppConfig {
     pub show_shortcuts_hint: bool,
     #[serde(default = "default_preview_chars")]
     pub preview_chars: u32,
+    #[serde(default)]
+    pub use_counts: HashMap<String, u32>,
+    #[serde(default = "default_frecency_weight")]
+    pub frecency_weight: u32,
+    #[serde(default = "default_frecency_half_life_hours")]
+    pub frecency_half_life_hours: u32,
+    #[serde(default)]
+    pub saved_searches: Vec<SavedSearch>,
+    /// How long the file watcher waits without new events before applying a
+    /// batch of changes.
+    #[serde(default = "default_watch_quiet_ms")]
+    pub watch_quiet_ms: u32,
+}
+
+/// A folder indexed into the prompt library next to `prompts_dir`.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct PromptRoot {
+    pub path: String,
+    #[serde(default)]
+    pub label: String,
+    #[serde(default = "default_root_enabled")]
+    pub enabled: bool,
+}
+
+impl AppConfig {
+    /// Enabled roots to index. `prompts_dir` always comes first because new
+    /// prompts are created there; a `prompt_roots` entry with the same path only
+    /// lends it a label.
+    pub fn active_roots(&self) -> Vec<PromptRoot> {
+        let mut roots: Vec<PromptRoot> = Vec::new();
+        if !self.prompts_dir.trim().is_empty() {
+            let label = self
+                .prompt_roots
+                .iter()
+                .find(|root| root.path == self.prompts_dir)
+                .map(|root| root.label.clone())
+                .unwrap_or_default();
+            roots.push(PromptRoot {
+                path: self.prompts_dir.clone(),
+                label,
+                enabled: true,
+            });
+        }
+        for root in &self.prompt_roots {
+            if !root.enabled || root.path.trim().is_empty() {
+                continue;
+            }
+            if roots.iter().any(|existing| existing.path == root.path) {
+                continue;
+            }
+            roots.push(root.clone());
+        }
+        roots
+    }
+}
+
+/// A named query the user can rerun from the launcher or its own hotkey.
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub id: String,
+    pub name: String,
+    pub query: String,
+    #[serde(default)]
+    pub favorites_only: bool,
+    #[serde(default)]
+    pub hotkey: Option<String>,
+    /// Run the query as a regex, like the search box's regex mode.
+    #[serde(default)]
+    pub regex: bool,
 }
 
 impl Default for AppConfig {
     fn default() -> Self {
         Self {
             prompts_dir: String::new(),
+            prompt_roots: Vec::new(),
             auto_paste: true,
             append_clipboard: false,
             hotkey: "Alt+Space".to_string(),
@@ -47,10 +121,19 @@ impl Default for AppConfig {
             top_tags_limit: default_top_tags_limit(),
             show_shortcuts_hint: default_show_shortcuts_hint(),
             preview_chars: default_preview_chars(),
+            use_counts: HashMap::new(),
+            frecency_weight: default_frecency_weight(),
+            frecency_half_life_hours: default_frecency_half_life_hours(),
+            saved_searches: Vec::new(),
+            watch_quiet_ms: default_watch_quiet_ms(),
         }
     }
 }
 
+fn default_root_enabled() -> bool {
+    true
+}
+
 fn default_top_tags_limit() -> u32 {
     8
 }
@@ -63,6 +146,18 @@ fn default_preview_chars() -> u32 {
     50
 }
 
+fn default_frecency_weight() -> u32 {
+    100
+}
+
+fn default_frecency_half_life_hours() -> u32 {
+    72
+}
+
+fn default_watch_quiet_ms() -> u32 {
+    300
+}
+
 pub fn load_or_init(app: &AppHandle) -> Result<AppConfig, String> {
     let path = config_path(app)?;
     if !path.exists() {
@@ -108,6 +203,12 @@ fn config_path(app: &AppHandle) -> Result<PathBuf, String> {
         .map_err(|e| format!("resolve config path failed: {e}"))
 }
 
+pub fn index_cache_path(app: &AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .resolve(INDEX_CACHE_FILENAME, BaseDirectory::AppData)
+        .map_err(|e| format!("resolve index cache path failed: {e}"))
+}
+
 fn default_prompts_dir(app: &AppHandle) -> Result<PathBuf, String> {
     app.path()
         .resolve("PromptLauncher/Prompts", BaseDirectory::Document)
@@ -1,3 +1,5 @@
 pub const EVENT_LAUNCHER_SHOWN: &str = "launcher-shown";
 pub const EVENT_PROMPTS_UPDATED: &str = "prompts-updated";
+pub const EVENT_PROMPTS_CHANGED: &str = "prompts-changed";
+pub const EVENT_SAVED_SEARCH_TRIGGERED: &str = "saved-search-triggered";
 pub const MAIN_WINDOW_LABEL: &str = "main";
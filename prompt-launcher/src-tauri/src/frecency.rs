@@ -0,0 +1,110 @@
+use std::collections::HashMap;
+
+use crate::config::AppConfig;
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+// Share of the frequency signal kept once a prompt has not been used for a long
+// time, so long-standing favourites of the user don't drop to zero.
+const RECENCY_FLOOR: f64 = 0.25;
+const FAVORITE_FACTOR: f64 = 0.5;
+/// Upper bound on a prompt's boost. It reorders results within a match tier
+/// but must not lift a prompt into a better tier, whatever the weight.
+pub const FRECENCY_BOOST_MAX: i32 = 400;
+
+/// Score bonus per prompt id, subtracted from the match score (lower ranks
+/// first). Combines `ln(1 + uses)` with an exponential decay on the last use
+/// from `recent_meta`, plus a flat bonus for favorites. Usage is ignored while
+/// recent tracking is off.
+pub fn frecency_boosts(config: &AppConfig, now_ms: i64) -> HashMap<String, i32> {
+    let mut boosts = HashMap::new();
+    let weight = config.frecency_weight as f64;
+    if weight <= 0.0 {
+        return boosts;
+    }
+    let half_life = config.frecency_half_life_hours.max(1) as f64;
+
+    let use_counts = config.use_counts.iter().filter(|_| config.recent_enabled);
+    for (id, count) in use_counts {
+        let recency = config
+            .recent_meta
+            .get(id)
+            .map(|last_used| {
+                let age_hours = now_ms.saturating_sub(*last_used).max(0) as f64 / MS_PER_HOUR;
+                0.5f64.powf(age_hours / half_life)
+            })
+            .unwrap_or(0.0);
+        let frequency = (1.0 + *count as f64).ln();
+        let boost = weight * frequency * (RECENCY_FLOOR + recency);
+        boosts.insert(id.clone(), boost.round() as i32);
+    }
+
+    for id in &config.favorites {
+        let favorite = (weight * FAVORITE_FACTOR).round() as i32;
+        *boosts.entry(id.clone()).or_insert(0) += favorite;
+    }
+
+    for boost in boosts.values_mut() {
+        *boost = (*boost).min(FRECENCY_BOOST_MAX);
+    }
+    boosts
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: i64 = 3_600_000;
+
+    #[test]
+    fn frequent_and_recent_uses_boost_more() {
+        let mut config = AppConfig::default();
+        let now = 1_000 * HOUR_MS;
+        config.use_counts.insert("often".to_string(), 10);
+        config.use_counts.insert("once".to_string(), 1);
+        config.use_counts.insert("stale".to_string(), 10);
+        config.recent_meta.insert("often".to_string(), now);
+        config.recent_meta.insert("once".to_string(), now);
+        config.recent_meta.insert("stale".to_string(), now - 500 * HOUR_MS);
+
+        let boosts = frecency_boosts(&config, now);
+        assert!(boosts["often"] > boosts["once"]);
+        assert!(boosts["often"] > boosts["stale"]);
+        assert!(boosts["stale"] > 0);
+    }
+
+    #[test]
+    fn favorites_get_flat_boost() {
+        let mut config = AppConfig::default();
+        config.favorites.push("fav".to_string());
+        let boosts = frecency_boosts(&config, 0);
+        let expected = (config.frecency_weight as f64 * FAVORITE_FACTOR).round() as i32;
+        assert_eq!(boosts["fav"], expected);
+    }
+
+    #[test]
+    fn boosts_are_capped_and_follow_recent_enabled() {
+        let mut config = AppConfig {
+            frecency_weight: 1000,
+            ..Default::default()
+        };
+        config.use_counts.insert("a".to_string(), 500);
+        config.recent_meta.insert("a".to_string(), 0);
+        config.favorites.push("a".to_string());
+        assert_eq!(frecency_boosts(&config, 0)["a"], FRECENCY_BOOST_MAX);
+
+        config.recent_enabled = false;
+        config.favorites.clear();
+        assert!(frecency_boosts(&config, 0).is_empty());
+    }
+
+    #[test]
+    fn zero_weight_disables_boosts() {
+        let mut config = AppConfig {
+            frecency_weight: 0,
+            ..Default::default()
+        };
+        config.use_counts.insert("a".to_string(), 5);
+        config.favorites.push("a".to_string());
+        assert!(frecency_boosts(&config, 0).is_empty());
+    }
+}
@@ -0,0 +1,196 @@
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::Path;
+use std::time::UNIX_EPOCH;
+
+use crate::config::PromptRoot;
+use crate::prompts::PromptEntry;
+use crate::tag_policy::TagPolicy;
+use crate::tag_rules::tag_rules_path;
+use crate::tags_meta::tags_meta_path;
+
+pub const INDEX_CACHE_FILENAME: &str = "index-cache.json";
+const INDEX_CACHE_VERSION: u32 = 1;
+
+/// Modification time and size of a file, the key for reusing its entry.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub struct FileStamp {
+    pub mtime_ms: u64,
+    pub size: u64,
+}
+
+impl FileStamp {
+    pub fn from_metadata(metadata: &fs::Metadata) -> Option<Self> {
+        let mtime = metadata.modified().ok()?.duration_since(UNIX_EPOCH).ok()?;
+        Some(Self {
+            mtime_ms: mtime.as_millis() as u64,
+            size: metadata.len(),
+        })
+    }
+
+    pub fn of(path: &Path) -> Option<Self> {
+        Self::from_metadata(&fs::metadata(path).ok()?)
+    }
+}
+
+/// The per-root files and the library policy that shape every entry of a
+/// root besides the prompt itself. When any changes, the root's entries are
+/// re-read.
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize, Deserialize)]
+pub struct RootStamp {
+    pub tags_meta: Option<FileStamp>,
+    pub tag_rules: Option<FileStamp>,
+    #[serde(default)]
+    pub policy: TagPolicy,
+}
+
+impl RootStamp {
+    pub fn of(root: &Path, policy: &TagPolicy) -> Self {
+        Self {
+            tags_meta: FileStamp::of(&tags_meta_path(root)),
+            tag_rules: FileStamp::of(&tag_rules_path(root)),
+            policy: policy.clone(),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize)]
+struct CachedPrompt {
+    stamp: FileStamp,
+    prompt: PromptEntry,
+}
+
+/// Indexed prompts from the previous run, so startup only re-reads files
+/// whose stamp changed.
+#[derive(Debug, Default, Serialize, Deserialize)]
+#[serde(default)]
+pub struct IndexCache {
+    version: u32,
+    preview_chars: usize,
+    roots: HashMap<String, RootStamp>,
+    entries: HashMap<String, CachedPrompt>,
+    #[serde(skip)]
+    next_roots: HashMap<String, RootStamp>,
+    #[serde(skip)]
+    next_entries: HashMap<String, CachedPrompt>,
+    #[serde(skip)]
+    dirty: bool,
+}
+
+impl IndexCache {
+    /// Reads the cache, starting empty when it is missing, unreadable or from
+    /// another version.
+    pub fn load(path: &Path) -> Self {
+        let Ok(data) = fs::read_to_string(path) else {
+            return Self::default();
+        };
+        match serde_json::from_str::<Self>(&data) {
+            Ok(cache) if cache.version == INDEX_CACHE_VERSION => cache,
+            Ok(_) => Self::default(),
+            Err(err) => {
+                eprintln!("[index_cache] parse failed: {err}");
+                Self::default()
+            }
+        }
+    }
+
+    pub fn save(&mut self, path: &Path) -> Result<(), String> {
+        if let Some(parent) = path.parent() {
+            fs::create_dir_all(parent)
+                .map_err(|e| format!("create index cache dir failed: {e}"))?;
+        }
+        let data = serde_json::to_string(self)
+            .map_err(|e| format!("serialize index cache failed: {e}"))?;
+        fs::write(path, data).map_err(|e| format!("write index cache failed: {e}"))?;
+        self.dirty = false;
+        Ok(())
+    }
+
+    /// Whether anything worth saving changed since the last save.
+    pub fn is_dirty(&self) -> bool {
+        self.dirty
+    }
+
+    /// Starts indexing `root`. Returns false when its cached entries cannot
+    /// be reused because its metadata or the preview length changed.
+    pub fn begin_root(&mut self, root: &str, stamp: RootStamp, preview_chars: usize) -> bool {
+        let reusable = self.preview_chars == preview_chars && self.roots.get(root) == Some(&stamp);
+        self.next_roots.insert(root.to_string(), stamp);
+        reusable
+    }
+
+    /// The cached entries under `roots`, ordered by id, without checking them
+    /// against the files. Roots the last pass did not index are skipped.
+    pub fn prompts(&self, roots: &[PromptRoot]) -> Vec<PromptEntry> {
+        let roots: Vec<&Path> = roots
+            .iter()
+            .filter(|root| self.roots.contains_key(&root.path))
+            .map(|root| Path::new(&root.path))
+            .collect();
+        let mut prompts: Vec<PromptEntry> = self
+            .entries
+            .values()
+            .filter(|cached| {
+                let path = Path::new(&cached.prompt.path);
+                roots.iter().any(|root| path.starts_with(root))
+            })
+            .map(|cached| cached.prompt.clone())
+            .collect();
+        prompts.sort_by(|a, b| a.id.cmp(&b.id));
+        prompts
+    }
+
+    pub fn get(&self, id: &str, stamp: FileStamp) -> Option<&PromptEntry> {
+        self.entries
+            .get(id)
+            .filter(|cached| cached.stamp == stamp)
+            .map(|cached| &cached.prompt)
+    }
+
+    /// Records an entry for the next cache; `fresh` marks one that was read
+    /// from disk rather than reused.
+    pub fn insert(&mut self, prompt: &PromptEntry, stamp: FileStamp, fresh: bool) {
+        self.dirty |= fresh;
+        self.next_entries.insert(
+            prompt.id.clone(),
+            CachedPrompt {
+                stamp,
+                prompt: prompt.clone(),
+            },
+        );
+    }
+
+    /// Updates one entry outside a full pass, e.g. after the watcher re-read
+    /// its file.
+    pub fn upsert(&mut self, prompt: &PromptEntry) {
+        let Some(stamp) = FileStamp::of(Path::new(&prompt.path)) else {
+            return;
+        };
+        self.dirty = true;
+        self.entries.insert(
+            prompt.id.clone(),
+            CachedPrompt {
+                stamp,
+                prompt: prompt.clone(),
+            },
+        );
+    }
+
+    pub fn remove(&mut self, id: &str) {
+        self.dirty |= self.entries.remove(id).is_some();
+    }
+
+    /// Replaces the cached state with what the pass recorded; entries for
+    /// files that were not seen again are dropped.
+    pub fn finish(&mut self, preview_chars: usize) {
+        self.dirty |= self.version != INDEX_CACHE_VERSION
+            || self.preview_chars != preview_chars
+            || self.next_roots != self.roots
+            || self.next_entries.len() != self.entries.len();
+        self.version = INDEX_CACHE_VERSION;
+        self.preview_chars = preview_chars;
+        self.roots = std::mem::take(&mut self.next_roots);
+        self.entries = std::mem::take(&mut self.next_entries);
+    }
+}
@@ -2,13 +2,25 @@ mod commands;
 mod config;
 mod constants;
 mod domain;
+mod frecency;
+mod index_cache;
 mod infrastructure;
 mod lifecycle;
 mod prompts;
+mod query;
+mod regex_search;
+mod search_index;
 mod services;
 mod state;
+mod tag_complete;
+mod tag_edit;
+mod tag_policy;
+mod tag_rules;
+mod tag_stats;
+mod tag_suggest;
 mod tags_meta;
 mod usecase;
+mod watch_batch;
 
 #[cfg(target_os = "windows")]
 mod autostart;
@@ -16,14 +28,14 @@ mod autostart;
 mod win;
 
 use crate::commands::*;
-use crate::config::load_or_init;
-use crate::prompts::index_prompts;
+use crate::config::{index_cache_path, load_or_init};
 use crate::services::prompts_service::PromptsService;
 use crate::services::window_service::WindowService;
 use crate::state::AppState;
 use std::fs;
 use std::path::PathBuf;
 use std::sync::Arc;
+use std::thread;
 use tauri::Manager;
 
 #[cfg_attr(mobile, tauri::mobile_entry_point)]
@@ -37,13 +49,9 @@ pub fn run() {
             let handle = app.handle();
             let config = load_or_init(handle)
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            let preview_chars = PromptsService::clamp_preview_chars(config.preview_chars) as usize;
-
             let dir = PathBuf::from(&config.prompts_dir);
             fs::create_dir_all(&dir)
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            PromptsService::seed_prompts_if_empty(&dir, preview_chars)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
 
             #[cfg(target_os = "windows")]
             {
@@ -53,22 +61,32 @@ pub fn run() {
             }
 
             let hotkey = config.hotkey.clone();
-            let state = Arc::new(AppState::new(config));
-            let prompts = index_prompts(&dir, preview_chars);
-            {
-                let mut lock = state.prompts.write().unwrap();
-                *lock = prompts;
+            let saved_searches = config.saved_searches.clone();
+            let mut state = AppState::new(config);
+            match index_cache_path(handle) {
+                Ok(cache_path) => state = state.with_index_cache(cache_path),
+                Err(error) => eprintln!("[index_cache] {error}"),
             }
+            let state = Arc::new(state);
+            // The cached list is served until the files are re-read below.
+            PromptsService::load_cached_prompts(&state);
 
             app.manage(state.clone());
             if let Err(error) = WindowService::update_hotkey_registration(&handle, &state, &hotkey)
             {
                 eprintln!("[hotkey] register failed: {error}");
             }
+            if let Err(error) =
+                WindowService::sync_saved_search_hotkeys(&handle, &state, &saved_searches)
+            {
+                eprintln!("[hotkey] saved search register failed: {error}");
+            }
             WindowService::init_tray(app)
                 .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
-            PromptsService::start_watcher(handle.clone(), state.clone(), dir)
-                .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+            let (startup_handle, startup_state) = (handle.clone(), state.clone());
+            thread::spawn(move || {
+                PromptsService::finish_startup(startup_handle, startup_state, dir)
+            });
             if cfg!(debug_assertions) {
                 let _ = WindowService::request_show(&handle, &state);
             }
@@ -80,7 +98,11 @@ pub fn run() {
             frontend_ready,
             list_prompts,
             search_prompts,
+            search_prompts_page,
+            search_prompt_hits,
+            get_tag_stats,
             set_prompts_dir,
+            set_prompt_roots,
             create_prompt_file,
             open_prompt_path,
             delete_prompt_files,
@@ -92,10 +114,35 @@ pub fn run() {
             push_recent,
             set_recent_enabled,
             update_prompt_tags,
+            set_folder_tags,
+            suggest_prompt_tags,
+            complete_tags,
+            list_tag_aliases,
+            set_tag_alias,
+            remove_tag_alias,
+            merge_tag_aliases,
+            get_tag_policy,
+            set_tag_policy,
+            list_tag_info,
+            set_tag_info,
+            delete_tag_info,
+            get_tag_rules,
+            set_tag_rules,
+            preview_tag_rule,
+            rename_tag,
+            merge_tags,
+            delete_tag,
             set_top_tags_scope,
             set_top_tags_limit,
             set_preview_chars,
             set_show_shortcuts_hint,
+            set_frecency,
+            set_watch_quiet_ms,
+            list_saved_searches,
+            create_saved_search,
+            update_saved_search,
+            delete_saved_search,
+            run_saved_search,
             clear_recent,
             capture_active_window,
             focus_last_window
@@ -1,12 +1,23 @@
-use serde::Serialize;
-use std::collections::HashSet;
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use unicode_normalization::char::canonical_combining_class;
+use unicode_normalization::UnicodeNormalization;
 use walkdir::WalkDir;
 
-use crate::tags_meta::{load_tags_meta, resolve_tags_for_path, TagsMeta};
+use crate::config::PromptRoot;
+use crate::index_cache::{FileStamp, IndexCache, RootStamp};
+use crate::query::{parse_query, Clause, Query, QueryField, QueryGroup};
+use crate::search_index::{IndexedDoc, SearchIndex};
+use crate::tag_policy::{default_policy, TagPolicy};
+use crate::tag_rules::{load_tag_rules, TagRules};
+use crate::tags_meta::{
+    canonical_tag, folder_keys, is_newer_version, path_to_key, read_tags_meta,
+    resolve_tags_for_path, TagsMeta,
+};
 
-#[derive(Debug, Clone, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
 pub struct PromptEntry {
     pub id: String,
     pub title: String,
@@ -14,73 +25,581 @@ pub struct PromptEntry {
     pub preview: String,
     pub tags: Vec<String>,
     pub path: String,
+    /// Label of the prompt root the file was indexed from.
+    pub source: String,
+}
+
+/// Optional restrictions and ranking signals for `search_prompts`.
+#[derive(Debug, Default)]
+pub struct SearchOptions<'a> {
+    /// Only prompts whose id is in the set are returned.
+    pub allowed_ids: Option<&'a HashSet<String>>,
+    /// Per-id bonus subtracted from the match score of non-empty queries.
+    pub boosts: Option<&'a HashMap<String, i32>>,
+    /// Tag aliases applied to `#tag` clauses before matching.
+    pub aliases: Option<&'a HashMap<String, String>>,
+    /// Rules for `#tag` clauses; the default policy when unset.
+    pub tag_policy: Option<&'a TagPolicy>,
+}
+
+/// Which part of a prompt decided its rank.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum MatchField {
+    Title,
+    Tags,
+    Content,
+    Body,
+    Path,
+}
+
+/// Character offsets `[start, end)` into the highlighted text.
+pub type HighlightRange = (usize, usize);
+
+#[derive(Debug, Clone, Default, PartialEq, Eq, Serialize)]
+pub struct Highlights {
+    pub title: Vec<HighlightRange>,
+    pub preview: Vec<HighlightRange>,
+    /// One list per entry of `PromptEntry.tags`.
+    pub tags: Vec<Vec<HighlightRange>>,
+}
+
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchHit {
+    pub prompt: PromptEntry,
+    pub score: i32,
+    pub matched_field: Option<MatchField>,
+    pub highlights: Highlights,
 }
 
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+pub struct TagCount {
+    pub tag: String,
+    pub count: usize,
+}
+
+/// One page of ranked results. `total` and `tag_counts` cover every match, not
+/// just the returned slice.
+#[derive(Debug, Clone, Serialize)]
+pub struct SearchPage {
+    pub items: Vec<PromptEntry>,
+    pub total: usize,
+    pub offset: usize,
+    pub tag_counts: Vec<TagCount>,
+}
+
+struct RankedPrompt {
+    id: usize,
+    score: i32,
+    field: Option<MatchField>,
+    group: usize,
+}
+
+// Below this many strict results the typo-tolerant pass kicks in.
+const TYPO_FALLBACK_MIN_RESULTS: usize = 5;
+const TYPO_SCORE_BASE: i32 = 1_000_000;
+
 pub fn search_prompts(
     prompts: &[PromptEntry],
+    index: &SearchIndex,
     query: &str,
     limit: usize,
+    options: &SearchOptions,
 ) -> Vec<PromptEntry> {
-    let trimmed = query.trim().to_lowercase();
-    if trimmed.is_empty() {
-        return prompts.iter().take(limit).cloned().collect();
-    }
-
-    let (tags, terms) = split_query(&trimmed);
-    let mut results: Vec<(i32, PromptEntry)> = Vec::new();
+    let parsed = parse_with_aliases(query, options);
+    rank_prompts(prompts, index, &parsed, options)
+        .into_iter()
+        .take(limit)
+        .map(|ranked| prompts[ranked.id].clone())
+        .collect()
+}
 
-    for prompt in prompts {
-        if !tags.is_empty() && !tags_match(prompt, &tags) {
-            continue;
-        }
+pub fn search_page(
+    prompts: &[PromptEntry],
+    index: &SearchIndex,
+    query: &str,
+    offset: usize,
+    limit: usize,
+    options: &SearchOptions,
+) -> SearchPage {
+    let ranked = match_ids(prompts, index, query, options);
+    page_from_ids(prompts, &ranked, offset, limit)
+}
 
-        let score = if terms.is_empty() {
-            Some(0)
-        } else {
-            score_prompt(prompt, &terms)
-        };
+/// Positions of every prompt matching `query`, in ranked order.
+pub fn match_ids(
+    prompts: &[PromptEntry],
+    index: &SearchIndex,
+    query: &str,
+    options: &SearchOptions,
+) -> Vec<usize> {
+    let parsed = parse_with_aliases(query, options);
+    rank_prompts(prompts, index, &parsed, options)
+        .into_iter()
+        .map(|ranked| ranked.id)
+        .collect()
+}
 
-        if let Some(score) = score {
-            results.push((score, prompt.clone()));
+/// Builds a page from prompt positions that are already in ranked order.
+pub fn page_from_ids(
+    prompts: &[PromptEntry],
+    ranked: &[usize],
+    offset: usize,
+    limit: usize,
+) -> SearchPage {
+    let mut counts: HashMap<&str, usize> = HashMap::new();
+    for id in ranked {
+        for tag in &prompts[*id].tags {
+            *counts.entry(tag.as_str()).or_insert(0) += 1;
         }
     }
+    let mut tag_counts: Vec<TagCount> = counts
+        .into_iter()
+        .map(|(tag, count)| TagCount {
+            tag: tag.to_string(),
+            count,
+        })
+        .collect();
+    tag_counts.sort_by(|a, b| b.count.cmp(&a.count).then_with(|| a.tag.cmp(&b.tag)));
 
-    results.sort_by_key(|(score, _)| *score);
-    results
+    SearchPage {
+        items: ranked
+            .iter()
+            .skip(offset)
+            .take(limit)
+            .map(|id| prompts[*id].clone())
+            .collect(),
+        total: ranked.len(),
+        offset,
+        tag_counts,
+    }
+}
+
+pub fn search_hits(
+    prompts: &[PromptEntry],
+    index: &SearchIndex,
+    query: &str,
+    limit: usize,
+    options: &SearchOptions,
+) -> Vec<SearchHit> {
+    let parsed = parse_with_aliases(query, options);
+    rank_prompts(prompts, index, &parsed, options)
         .into_iter()
         .take(limit)
-        .map(|(_, prompt)| prompt)
+        .map(|ranked| {
+            let prompt = &prompts[ranked.id];
+            let highlights = match (parsed.groups.get(ranked.group), index.doc(ranked.id)) {
+                (Some(group), Some(doc)) => highlight_group(prompt, doc, group),
+                _ => Highlights {
+                    tags: vec![Vec::new(); prompt.tags.len()],
+                    ..Default::default()
+                },
+            };
+            SearchHit {
+                prompt: prompt.clone(),
+                score: ranked.score,
+                matched_field: ranked.field,
+                highlights,
+            }
+        })
         .collect()
 }
 
-pub fn index_prompts(dir: &Path, preview_chars: usize) -> Vec<PromptEntry> {
-    let meta = match load_tags_meta(dir) {
-        Ok(meta) => meta,
-        Err(err) => {
-            eprintln!("[tags_meta] load failed: {err}");
-            TagsMeta::default()
-        }
+fn rank_prompts(
+    prompts: &[PromptEntry],
+    index: &SearchIndex,
+    query: &Query,
+    options: &SearchOptions,
+) -> Vec<RankedPrompt> {
+    let is_allowed = |prompt: &PromptEntry| {
+        options
+            .allowed_ids
+            .is_none_or(|ids| ids.contains(&prompt.id))
     };
-    let mut entries = Vec::new();
-    for entry in WalkDir::new(dir).follow_links(true).into_iter().flatten() {
-        if !entry.file_type().is_file() {
+    // Ties are broken by path so pages stay stable between requests.
+    let sort = |results: &mut Vec<RankedPrompt>| {
+        results.sort_by(|a, b| {
+            a.score
+                .cmp(&b.score)
+                .then_with(|| prompts[a.id].path.cmp(&prompts[b.id].path))
+        });
+    };
+    if query.is_empty() {
+        let mut results: Vec<RankedPrompt> = prompts
+            .iter()
+            .enumerate()
+            .filter(|(_, prompt)| is_allowed(prompt))
+            .map(|(id, _)| RankedPrompt {
+                id,
+                score: 0,
+                field: None,
+                group: 0,
+            })
+            .collect();
+        sort(&mut results);
+        return results;
+    }
+
+    let stats = BodyStats::new(index, query);
+    let mut results = Vec::new();
+
+    for id in query_candidates(index, query) {
+        let (Some(prompt), Some(doc)) = (prompts.get(id), index.doc(id)) else {
+            continue;
+        };
+        if !is_allowed(prompt) {
             continue;
         }
-        let path = entry.path();
-        if !is_prompt_file(path) {
+        if let Some((score, field, group)) = score_query(prompt, doc, query, &stats) {
+            results.push(RankedPrompt {
+                id,
+                score: score - boost(options, prompt),
+                field,
+                group,
+            });
+        }
+    }
+
+    sort(&mut results);
+    if results.len() < TYPO_FALLBACK_MIN_RESULTS {
+        let matched: HashSet<usize> = results.iter().map(|ranked| ranked.id).collect();
+        let mut typos: Vec<RankedPrompt> = typo_matches(prompts, index, query)
+            .into_iter()
+            .filter(|ranked| !matched.contains(&ranked.id))
+            .filter_map(|mut ranked| {
+                let prompt = &prompts[ranked.id];
+                if !is_allowed(prompt) {
+                    return None;
+                }
+                ranked.score -= boost(options, prompt);
+                Some(ranked)
+            })
+            .collect();
+        sort(&mut typos);
+        results.extend(typos);
+    }
+    results
+}
+
+fn boost(options: &SearchOptions, prompt: &PromptEntry) -> i32 {
+    options
+        .boosts
+        .and_then(|boosts| boosts.get(&prompt.id))
+        .copied()
+        .unwrap_or(0)
+}
+
+/// Edits tolerated for a fuzzy term; short terms must match strictly.
+fn max_typo_edits(term: &str) -> usize {
+    match term.chars().count() {
+        0..=3 => 0,
+        4..=7 => 1,
+        _ => 2,
+    }
+}
+
+fn is_typo_term(clause: &Clause) -> bool {
+    matches!(
+        clause,
+        Clause::Text {
+            field: QueryField::Any,
+            phrase: false,
+            negated: false,
+            text,
+        } if max_typo_edits(text) > 0
+    )
+}
+
+/// Fallback pass matching plain terms to indexed words within a small edit
+/// distance. Scores start at `TYPO_SCORE_BASE`, so these always rank after
+/// strict matches; fewer edits and title words rank first.
+fn typo_matches(prompts: &[PromptEntry], index: &SearchIndex, query: &Query) -> Vec<RankedPrompt> {
+    let mut best: HashMap<usize, RankedPrompt> = HashMap::new();
+
+    for (group_index, group) in query.groups.iter().enumerate() {
+        let typo_terms: Vec<&str> = group
+            .clauses
+            .iter()
+            .filter(|clause| is_typo_term(clause))
+            .filter_map(|clause| match clause {
+                Clause::Text { text, .. } => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        if typo_terms.is_empty() {
             continue;
         }
-        if let Some(prompt) = read_prompt(path, dir, &meta, preview_chars) {
+        let strict = QueryGroup {
+            clauses: group
+                .clauses
+                .iter()
+                .filter(|clause| !is_typo_term(clause))
+                .cloned()
+                .collect(),
+        };
+
+        // Per term: doc id -> (edits, matched word is not in the title).
+        let mut term_matches: Vec<HashMap<usize, (usize, bool)>> = Vec::new();
+        for term in &typo_terms {
+            let mut docs: HashMap<usize, (usize, bool)> = HashMap::new();
+            for (word, edits) in index.similar_words(term, max_typo_edits(term)) {
+                for &id in index.word_docs(word) {
+                    let id = id as usize;
+                    let outside_title = index.doc(id).is_none_or(|doc| !doc.title.contains(word));
+                    let entry = docs.entry(id).or_insert((edits, outside_title));
+                    *entry = (*entry).min((edits, outside_title));
+                }
+            }
+            term_matches.push(docs);
+        }
+
+        let candidates: HashSet<usize> = term_matches
+            .iter()
+            .flat_map(|docs| docs.keys().copied())
+            .collect();
+        for id in candidates {
+            let (Some(prompt), Some(doc)) = (prompts.get(id), index.doc(id)) else {
+                continue;
+            };
+            let mut edits = 0;
+            let mut in_title = true;
+            let mut matched = true;
+            for (term, docs) in typo_terms.iter().zip(&term_matches) {
+                if score_match(&doc.full, term).is_some() {
+                    in_title &= score_match(&doc.title, term).is_some();
+                } else if let Some((term_edits, outside_title)) = docs.get(&id) {
+                    edits += term_edits;
+                    in_title &= !outside_title;
+                } else {
+                    matched = false;
+                    break;
+                }
+            }
+            if !matched {
+                continue;
+            }
+            let Some((_, strict_field)) = score_group(prompt, doc, &strict, &BodyStats::default())
+            else {
+                continue;
+            };
+            let field = if in_title {
+                MatchField::Title
+            } else {
+                strict_field.unwrap_or(MatchField::Content)
+            };
+            let score = TYPO_SCORE_BASE + edits as i32 * 1000 - if in_title { 500 } else { 0 };
+            let ranked = RankedPrompt {
+                id,
+                score,
+                field: Some(field),
+                group: group_index,
+            };
+            match best.get(&id) {
+                Some(existing) if existing.score <= ranked.score => {}
+                _ => {
+                    best.insert(id, ranked);
+                }
+            }
+        }
+    }
+
+    best.into_values().collect()
+}
+
+/// Indexes every root into one list with the library's tag `policy`. A file
+/// reachable from several (nested) roots is kept once, under the first root
+/// listed.
+pub fn index_roots(
+    roots: &[PromptRoot],
+    policy: &TagPolicy,
+    preview_chars: usize,
+) -> Vec<PromptEntry> {
+    walk_roots(roots, policy, preview_chars, None)
+}
+
+/// Like `index_roots`, but reuses entries from `cache` for files whose mtime
+/// and size are unchanged, and records the result back into it. A root's
+/// entries are all re-read when its `.tags.json` or tag rules changed.
+pub fn index_roots_cached(
+    roots: &[PromptRoot],
+    policy: &TagPolicy,
+    preview_chars: usize,
+    cache: &mut IndexCache,
+) -> Vec<PromptEntry> {
+    let entries = walk_roots(roots, policy, preview_chars, Some(&mut *cache));
+    cache.finish(preview_chars);
+    entries
+}
+
+fn walk_roots(
+    roots: &[PromptRoot],
+    policy: &TagPolicy,
+    preview_chars: usize,
+    mut cache: Option<&mut IndexCache>,
+) -> Vec<PromptEntry> {
+    let mut seen = HashSet::new();
+    let mut entries = Vec::new();
+    for root in roots {
+        let dir = Path::new(&root.path);
+        let stamp = RootStamp::of(dir, policy);
+        let reusable = cache
+            .as_deref_mut()
+            .is_some_and(|cache| cache.begin_root(&root.path, stamp, preview_chars));
+        let indexer = RootIndexer::new(root, policy);
+        for entry in WalkDir::new(dir).follow_links(true).into_iter().flatten() {
+            let path = entry.path();
+            if !entry.file_type().is_file() || !is_prompt_file(path) {
+                continue;
+            }
+            let id = path.to_string_lossy().to_string();
+            if seen.contains(&id) {
+                continue;
+            }
+            let stamp = cache
+                .as_ref()
+                .and_then(|_| entry.metadata().ok())
+                .and_then(|meta| FileStamp::from_metadata(&meta));
+            let cached = stamp
+                .filter(|_| reusable)
+                .and_then(|stamp| cache.as_deref()?.get(&id, stamp))
+                .cloned();
+            let (prompt, fresh) = match cached {
+                Some(mut prompt) => {
+                    prompt.source = indexer.source.clone();
+                    (prompt, false)
+                }
+                None => match indexer.read(path, preview_chars) {
+                    Some(prompt) => (prompt, true),
+                    None => continue,
+                },
+            };
+            if let (Some(cache), Some(stamp)) = (cache.as_deref_mut(), stamp) {
+                cache.insert(&prompt, stamp, fresh);
+            }
+            seen.insert(id);
             entries.push(prompt);
         }
     }
     entries
 }
 
+/// The root's label, or its folder name when no label was given.
+pub fn root_label(root: &PromptRoot) -> String {
+    let label = root.label.trim();
+    if !label.is_empty() {
+        return label.to_string();
+    }
+    Path::new(&root.path)
+        .file_name()
+        .map(|name| name.to_string_lossy().to_string())
+        .unwrap_or_else(|| root.path.clone())
+}
+
+/// Whether `dir` holds at least one prompt file; stops at the first one.
+pub fn has_prompt_files(dir: &Path) -> bool {
+    WalkDir::new(dir)
+        .follow_links(true)
+        .into_iter()
+        .flatten()
+        .any(|entry| entry.file_type().is_file() && is_prompt_file(entry.path()))
+}
+
+/// One root's `.tags.json` and tag rules, loaded once so single files can be
+/// re-read without walking the whole root. The library policy replaces the
+/// root's own, so every root extracts tags the same way.
+pub struct RootIndexer {
+    root: PathBuf,
+    /// `root` with symlinks resolved, for matching paths reported through
+    /// another spelling of it.
+    canonical_root: Option<PathBuf>,
+    source: String,
+    meta: TagsMeta,
+    rules: TagRules,
+}
+
+impl RootIndexer {
+    pub fn new(prompt_root: &PromptRoot, policy: &TagPolicy) -> Self {
+        let root = Path::new(&prompt_root.path);
+        let mut meta = match read_tags_meta(root) {
+            Ok(meta) => meta,
+            Err(err) => {
+                eprintln!("[tags_meta] load failed: {err}");
+                TagsMeta::default()
+            }
+        };
+        if is_newer_version(&meta) {
+            eprintln!(
+                "[tags_meta] {} is from a newer version, indexing it read-only",
+                root.display()
+            );
+        }
+        meta.policy = policy.clone();
+        let rules = match load_tag_rules(root).and_then(|file| TagRules::compile(&file.rules)) {
+            Ok(rules) => rules,
+            Err(err) => {
+                eprintln!("[tag_rules] load failed: {err}");
+                TagRules::default()
+            }
+        };
+        Self {
+            root: root.to_path_buf(),
+            canonical_root: root.canonicalize().ok(),
+            source: root_label(prompt_root),
+            meta,
+            rules,
+        }
+    }
+
+    /// `path` spelled under the root as configured, like the indexed ids,
+    /// even when it was reported through a symlink or with other casing.
+    /// `None` when it is outside the root.
+    pub fn resolve(&self, path: &Path) -> Option<PathBuf> {
+        if path.starts_with(&self.root) {
+            return Some(path.to_path_buf());
+        }
+        let canonical_root = self.canonical_root.as_ref()?;
+        let relative = canonicalize_existing(path)?
+            .strip_prefix(canonical_root)
+            .ok()?
+            .to_path_buf();
+        Some(self.root.join(relative))
+    }
+
+    /// Reads one prompt file; `None` for other files or unreadable ones.
+    pub fn read(&self, path: &Path, preview_chars: usize) -> Option<PromptEntry> {
+        if !is_prompt_file(path) {
+            return None;
+        }
+        let mut prompt = read_prompt(path, &self.root, &self.meta, &self.rules, preview_chars)?;
+        prompt.source = self.source.clone();
+        Some(prompt)
+    }
+}
+
+/// Canonicalizes the longest existing ancestor of `path` and appends the rest,
+/// so paths of deleted files resolve too.
+fn canonicalize_existing(path: &Path) -> Option<PathBuf> {
+    let mut missing = Vec::new();
+    let mut current = path;
+    loop {
+        if let Ok(canonical) = current.canonicalize() {
+            return Some(
+                missing
+                    .iter()
+                    .rev()
+                    .fold(canonical, |path, name| path.join(name)),
+            );
+        }
+        missing.push(current.file_name()?);
+        current = current.parent()?;
+    }
+}
+
 fn read_prompt(
     path: &Path,
     root: &Path,
     meta: &TagsMeta,
+    rules: &TagRules,
     preview_chars: usize,
 ) -> Option<PromptEntry> {
     let body = fs::read_to_string(path).ok()?;
@@ -89,13 +608,14 @@ fn read_prompt(
         .unwrap_or_default()
         .to_string_lossy()
         .to_string();
-    let mut fallback_tags: HashSet<String> = extract_tags(&title).into_iter().collect();
-    for tag in extract_path_tags(path, root) {
-        fallback_tags.insert(tag);
+    let fallback = fallback_tags(path, root, meta, &meta.policy);
+    let mut resolved = resolve_tags_for_path(meta, root, path, fallback);
+    // Rule tags are additive, like inherited folder tags.
+    resolved.extend(rules.tags_for(root, path, &body));
+    let mut tags = normalize_tags(resolved, &meta.aliases, &meta.policy);
+    if let Some(removed) = meta.removed_by_path.get(&path_to_key(root, path)) {
+        tags.retain(|tag| !removed.contains(tag));
     }
-    let fallback: Vec<String> = fallback_tags.into_iter().collect();
-    let resolved = resolve_tags_for_path(meta, root, path, fallback);
-    let mut tags = normalize_tags(resolved);
     tags.sort();
     let preview = make_preview(&body, preview_chars);
     let path_string = path.to_string_lossy().to_string();
@@ -107,9 +627,46 @@ fn read_prompt(
         preview,
         tags,
         path: path_string,
+        source: String::new(),
     })
 }
 
+/// Tags a prompt gets without a `.tags.json` override: `#tag`/`[tag]` in the
+/// file stem plus its folder tag, canonicalized and sorted.
+pub(crate) fn fallback_tags(
+    path: &Path,
+    root: &Path,
+    meta: &TagsMeta,
+    policy: &TagPolicy,
+) -> Vec<String> {
+    let title = path.file_stem().unwrap_or_default().to_string_lossy();
+    let mut raw = extract_tags(&title, policy);
+    raw.extend(extract_path_tags(path, root, policy));
+    let mut tags = normalize_tags(raw, &meta.aliases, policy);
+    tags.sort();
+    tags
+}
+
+/// Tags `path` gets from folder entries and tag rules on top of its own,
+/// normalized like `read_prompt` does.
+pub(crate) fn applied_tags(
+    path: &Path,
+    root: &Path,
+    meta: &TagsMeta,
+    rules: &TagRules,
+    policy: &TagPolicy,
+    body: &str,
+) -> Vec<String> {
+    let key = path_to_key(root, path);
+    let mut raw: Vec<String> = folder_keys(&key)
+        .filter_map(|folder| meta.tags_by_path.get(folder))
+        .flatten()
+        .cloned()
+        .collect();
+    raw.extend(rules.tags_for(root, path, body));
+    normalize_tags(raw, &meta.aliases, policy)
+}
+
 fn is_prompt_file(path: &Path) -> bool {
     match path.extension().and_then(|ext| ext.to_str()) {
         Some(ext) => matches!(ext.to_ascii_lowercase().as_str(), "md" | "txt"),
@@ -157,14 +714,14 @@ pub(crate) fn make_preview(body: &str, max_chars: usize) -> String {
     preview
 }
 
-fn extract_tags(title: &str) -> Vec<String> {
+fn extract_tags(title: &str, policy: &TagPolicy) -> Vec<String> {
     let mut tags = HashSet::new();
 
     let mut rest = title;
     while let Some(start) = rest.find('[') {
         if let Some(end) = rest[start + 1..].find(']') {
             let tag = &rest[start + 1..start + 1 + end];
-            if let Some(normalized) = normalize_tag(tag) {
+            if let Ok(normalized) = policy.normalize(tag) {
                 tags.insert(normalized);
             }
             rest = &rest[start + 1 + end + 1..];
@@ -175,7 +732,7 @@ fn extract_tags(title: &str) -> Vec<String> {
 
     for token in title.split_whitespace() {
         if let Some(tag) = token.strip_prefix('#') {
-            if let Some(normalized) = normalize_tag(tag) {
+            if let Ok(normalized) = policy.normalize(tag) {
                 tags.insert(normalized);
             }
         }
@@ -184,7 +741,13 @@ fn extract_tags(title: &str) -> Vec<String> {
     tags.into_iter().collect()
 }
 
-fn extract_path_tags(path: &Path, root: &Path) -> Vec<String> {
+pub(crate) const TAG_SEPARATOR: &str = "/";
+pub(crate) const TAG_MAX_DEPTH: usize = 5;
+
+/// The folder hierarchy as one nested tag, e.g. `Work/Email/a.txt` gives
+/// `work/email`. The tag stops above the first folder that is not a valid tag
+/// segment, so skipping it cannot invent a hierarchy.
+fn extract_path_tags(path: &Path, root: &Path, policy: &TagPolicy) -> Vec<String> {
     let mut tags = Vec::new();
     let Ok(relative) = path.strip_prefix(root) else {
         return tags;
@@ -192,38 +755,80 @@ fn extract_path_tags(path: &Path, root: &Path) -> Vec<String> {
     let Some(parent) = relative.parent() else {
         return tags;
     };
-    for component in parent.components() {
-        let name = component.as_os_str().to_string_lossy();
-        if let Some(tag) = normalize_tag(&name) {
-            tags.push(tag);
-        }
+    let segments: Vec<String> = parent
+        .components()
+        .map_while(|component| {
+            policy
+                .normalize_segment(&component.as_os_str().to_string_lossy())
+                .ok()
+        })
+        .take(TAG_MAX_DEPTH)
+        .collect();
+    if !segments.is_empty() {
+        tags.push(segments.join(TAG_SEPARATOR));
     }
     tags
 }
 
-pub(crate) fn normalize_tag(raw: &str) -> Option<String> {
-    let trimmed = raw.trim();
-    if trimmed.is_empty() {
-        return None;
+/// Search form of `text`: NFKC (which also folds full-width letters, digits
+/// and punctuation to their half-width forms) followed by lowercasing.
+pub fn normalize_text(text: &str) -> String {
+    if text.is_ascii() {
+        return text.to_ascii_lowercase();
     }
-    if trimmed.chars().count() > 10 {
-        return None;
-    }
-    if !trimmed.chars().all(is_allowed_tag_char) {
-        return None;
+    normalize_with_map(text).0
+}
+
+/// Like `normalize_text`, also returning for every output character the index
+/// of the source character it came from. Each base character is normalized
+/// together with its combining marks so the mapping stays exact.
+pub fn normalize_with_map(text: &str) -> (String, Vec<usize>) {
+    let chars: Vec<char> = text.chars().collect();
+    let mut normalized = String::with_capacity(text.len());
+    let mut map = Vec::with_capacity(chars.len());
+    let mut segment = String::new();
+    let mut start = 0;
+    while start < chars.len() {
+        let mut end = start + 1;
+        while end < chars.len() && canonical_combining_class(chars[end]) != 0 {
+            end += 1;
+        }
+        segment.clear();
+        segment.extend(&chars[start..end]);
+        for ch in segment.nfkc().flat_map(char::to_lowercase) {
+            normalized.push(ch);
+            map.push(start);
+        }
+        start = end;
     }
-    Some(trimmed.to_ascii_lowercase())
+    (normalized, map)
+}
+
+/// Whether `tag` is `filter` itself or nested under it, so `work` matches
+/// `work/email` but not `workshop`.
+pub(crate) fn tag_matches(tag: &str, filter: &str) -> bool {
+    tag.strip_prefix(filter)
+        .is_some_and(|rest| rest.is_empty() || rest.starts_with(TAG_SEPARATOR))
 }
 
-fn is_allowed_tag_char(ch: char) -> bool {
-    ch.is_ascii_alphanumeric() || (ch >= '\u{4E00}' && ch <= '\u{9FFF}')
+/// `tag` and each of its ancestors, shortest first: `a/b/c` gives `a`, `a/b`,
+/// `a/b/c`.
+pub(crate) fn tag_prefixes(tag: &str) -> impl Iterator<Item = &str> {
+    tag.match_indices(TAG_SEPARATOR)
+        .map(|(end, _)| &tag[..end])
+        .chain(std::iter::once(tag))
 }
 
-fn normalize_tags(raw: Vec<String>) -> Vec<String> {
+fn normalize_tags(
+    raw: Vec<String>,
+    aliases: &HashMap<String, String>,
+    policy: &TagPolicy,
+) -> Vec<String> {
     let mut seen = HashSet::new();
     let mut tags = Vec::new();
     for tag in raw {
-        if let Some(normalized) = normalize_tag(&tag) {
+        if let Ok(normalized) = policy.normalize(&tag) {
+            let normalized = canonical_tag(aliases, &normalized);
             if seen.insert(normalized.clone()) {
                 tags.push(normalized);
             }
@@ -232,46 +837,247 @@ fn normalize_tags(raw: Vec<String>) -> Vec<String> {
     tags
 }
 
-fn split_query(query: &str) -> (Vec<String>, Vec<String>) {
-    let mut tags = Vec::new();
-    let mut terms = Vec::new();
+fn parse_with_aliases(query: &str, options: &SearchOptions) -> Query {
+    let mut parsed = parse_query(query, options.tag_policy.unwrap_or(default_policy()));
+    if let Some(aliases) = options.aliases {
+        parsed.resolve_aliases(aliases);
+    }
+    parsed
+}
 
-    for token in query.split_whitespace() {
-        if let Some(tag) = token.strip_prefix('#') {
-            if let Some(normalized) = normalize_tag(tag) {
-                tags.push(normalized);
+fn tags_match(prompt: &PromptEntry, tags: &[String]) -> bool {
+    tags.iter()
+        .all(|tag| prompt.tags.iter().any(|t| tag_matches(t, tag)))
+}
+
+fn query_candidates(index: &SearchIndex, query: &Query) -> Vec<usize> {
+    let mut ids: Vec<usize> = query
+        .groups
+        .iter()
+        .flat_map(|group| index.candidates(&group.positive_tags(), &group.index_terms()))
+        .collect();
+    if query.groups.len() > 1 {
+        ids.sort_unstable();
+        ids.dedup();
+    }
+    ids
+}
+
+fn score_query(
+    prompt: &PromptEntry,
+    doc: &IndexedDoc,
+    query: &Query,
+    stats: &BodyStats,
+) -> Option<(i32, Option<MatchField>, usize)> {
+    query
+        .groups
+        .iter()
+        .enumerate()
+        .filter_map(|(index, group)| {
+            score_group(prompt, doc, group, stats).map(|(score, field)| (score, field, index))
+        })
+        .min_by_key(|(score, _, _)| *score)
+}
+
+fn score_group(
+    prompt: &PromptEntry,
+    doc: &IndexedDoc,
+    group: &QueryGroup,
+    stats: &BodyStats,
+) -> Option<(i32, Option<MatchField>)> {
+    let tags = group.positive_tags();
+    if !tags.is_empty() && !tags_match(prompt, &tags) {
+        return None;
+    }
+
+    let mut terms = Vec::new();
+    let mut field_score = 0;
+    let mut matched_field = if tags.is_empty() {
+        None
+    } else {
+        Some(MatchField::Tags)
+    };
+    for clause in &group.clauses {
+        match clause {
+            Clause::Tag { tag, negated: true } => {
+                if prompt.tags.iter().any(|t| tag_matches(t, tag)) {
+                    return None;
+                }
+            }
+            Clause::Tag { .. } => {}
+            Clause::Text {
+                field,
+                text,
+                phrase,
+                negated,
+            } => {
+                let haystack = match field {
+                    QueryField::Any => doc.full.as_str(),
+                    QueryField::Title => doc.title.as_str(),
+                    QueryField::Body => doc.body(),
+                    QueryField::Path => doc.path.as_str(),
+                };
+                // Exclusions and phrases need the literal text; fuzzy subsequence
+                // matching would exclude far too much.
+                if *negated {
+                    if haystack.contains(text.as_str()) {
+                        return None;
+                    }
+                    continue;
+                }
+                if *phrase && !haystack.contains(text.as_str()) {
+                    return None;
+                }
+                let clause_field = match field {
+                    QueryField::Any => {
+                        terms.push(text.clone());
+                        continue;
+                    }
+                    QueryField::Title => {
+                        field_score +=
+                            score_match(haystack, text).or_else(|| score_pinyin(doc, text))?;
+                        MatchField::Title
+                    }
+                    QueryField::Body => {
+                        field_score += score_match(haystack, text)?;
+                        MatchField::Body
+                    }
+                    QueryField::Path => {
+                        field_score += score_match(haystack, text)?;
+                        MatchField::Path
+                    }
+                };
+                matched_field.get_or_insert(clause_field);
             }
-        } else {
-            terms.push(token.to_string());
         }
     }
 
-    (tags, terms)
+    if terms.is_empty() {
+        return Some((field_score, matched_field));
+    }
+    let (score, field) = score_prompt(doc, &terms, stats)?;
+    Some((score + field_score, Some(field)))
 }
 
-fn tags_match(prompt: &PromptEntry, tags: &[String]) -> bool {
-    tags.iter().all(|tag| prompt.tags.iter().any(|t| t == tag))
+fn score_prompt(
+    doc: &IndexedDoc,
+    terms: &[String],
+    stats: &BodyStats,
+) -> Option<(i32, MatchField)> {
+    // Body matches rank by BM25 instead of where the term first shows up.
+    let content = match stats.bm25(doc, terms) {
+        Some(relevance) => Some(body_tier_score(relevance, terms.len())),
+        None => score_terms(&doc.full, terms),
+    }
+    .map(|score| (score.max(CONTENT_FLOOR), MatchField::Content));
+    // Title pinyin matches rank below literal title matches (-10000)
+    // but ahead of content matches.
+    let pinyin = score_pinyin_terms(doc, terms).map(|score| (score - 5000, MatchField::Title));
+    // Prioritize title matches with much higher weight (-10000)
+    // This ensures title matches always appear before content matches
+    let title = score_terms(&doc.title, terms).map(|score| (score - 10000, MatchField::Title));
+    // Tag matches sit in a fixed band of their own, so where the tag falls in
+    // the tag list can never push it below a body match.
+    let tags = score_terms(&doc.tags, terms).map(|score| {
+        (
+            TAG_TIER + score.clamp(-TAG_TIER_SPAN, TAG_TIER_SPAN),
+            MatchField::Tags,
+        )
+    });
+    [title, tags, pinyin, content]
+        .into_iter()
+        .flatten()
+        .min_by_key(|(score, _)| *score)
 }
 
-fn score_prompt(prompt: &PromptEntry, terms: &[String]) -> Option<i32> {
-    let tag_text = prompt.tags.join(" ").to_lowercase();
-    let title_text = prompt.title.to_lowercase();
-    let full_text = format!(
-        "{} {} {} {}",
-        prompt.title, prompt.preview, prompt.body, tag_text
-    )
-    .to_lowercase();
+const TAG_TIER: i32 = -3000;
+const TAG_TIER_SPAN: i32 = 500;
+// Best score a content match can reach. The gap to the worst tag score stays
+// wider than `FRECENCY_BOOST_MAX`, so boosts cannot reorder the tiers either.
+const CONTENT_FLOOR: i32 = -2000;
 
-    let mut best = score_terms(&full_text, terms)?;
-    // Prioritize title matches with much higher weight (-10000)
-    // This ensures title matches always appear before content matches
-    if let Some(score) = score_terms(&title_text, terms) {
-        best = best.min(score - 10000);
+const BM25_K1: f64 = 1.2;
+const BM25_B: f64 = 0.75;
+// Relevance at which a body match lands halfway through its tier.
+const BM25_HALF_TIER: f64 = 2.0;
+
+/// Corpus statistics for BM25 over prompt bodies, computed once per query.
+#[derive(Debug, Default)]
+struct BodyStats {
+    idf: HashMap<String, f64>,
+    avg_len: f64,
+}
+
+impl BodyStats {
+    fn new(index: &SearchIndex, query: &Query) -> Self {
+        let mut idf = HashMap::new();
+        for group in &query.groups {
+            for clause in &group.clauses {
+                if let Clause::Text {
+                    field: QueryField::Any,
+                    text,
+                    negated: false,
+                    ..
+                } = clause
+                {
+                    if !idf.contains_key(text) {
+                        idf.insert(text.clone(), index.body_idf(text));
+                    }
+                }
+            }
+        }
+        Self {
+            idf,
+            avg_len: index.avg_body_len(),
+        }
     }
-    if let Some(score) = score_terms(&tag_text, terms) {
-        best = best.min(score - 80);
+
+    /// BM25 relevance of the body, or `None` unless it contains every term
+    /// verbatim.
+    fn bm25(&self, doc: &IndexedDoc, terms: &[String]) -> Option<f64> {
+        if self.avg_len <= 0.0 {
+            return None;
+        }
+        let body = doc.body();
+        let length_norm = 1.0 - BM25_B + BM25_B * doc.body_len as f64 / self.avg_len;
+        let mut total = 0.0;
+        for term in terms {
+            let idf = *self.idf.get(term)?;
+            let tf = body.matches(term.as_str()).count() as f64;
+            if tf == 0.0 {
+                return None;
+            }
+            total += idf * tf * (BM25_K1 + 1.0) / (tf + BM25_K1 * length_norm);
+        }
+        Some(total)
     }
-    Some(best)
+}
+
+/// Maps BM25 relevance into `[-299, -200]` for a single term (another `-200`
+/// per extra term), which keeps body matches behind the title (`-10000`) and
+/// tag (`TAG_TIER`) tiers.
+fn body_tier_score(relevance: f64, term_count: usize) -> i32 {
+    let share = relevance / (relevance + BM25_HALF_TIER);
+    -200 * term_count as i32 - (99.0 * share).round() as i32
+}
+
+fn score_pinyin_terms(doc: &IndexedDoc, terms: &[String]) -> Option<i32> {
+    let mut total = 0;
+    for term in terms {
+        total += score_pinyin(doc, term)?;
+    }
+    Some(total)
+}
+
+/// Matches a term against the title's full pinyin (`youjian`) or its initials
+/// (`yjhf`), keeping whichever scores better.
+fn score_pinyin(doc: &IndexedDoc, term: &str) -> Option<i32> {
+    if doc.title_pinyin.is_empty() {
+        return None;
+    }
+    let full = score_match(&doc.title_pinyin, term);
+    let initials = score_match(&doc.title_initials, term);
+    full.into_iter().chain(initials).min()
 }
 
 fn score_terms(text: &str, terms: &[String]) -> Option<i32> {
@@ -283,64 +1089,201 @@ fn score_terms(text: &str, terms: &[String]) -> Option<i32> {
 }
 
 fn score_match(text: &str, term: &str) -> Option<i32> {
+    match_term(text, term, None)
+}
+
+/// Scores `term` against `text`, both already normalized. Positions and gaps
+/// are counted in characters, so CJK text scores like ASCII text of the same
+/// visible length. When `positions` is given it receives the character indices
+/// of the match: the best substring if there is one, otherwise the in-order
+/// subsequence.
+fn match_term(text: &str, term: &str, mut positions: Option<&mut Vec<usize>>) -> Option<i32> {
     if term.is_empty() {
         return Some(0);
     }
 
+    let term_len = term.chars().count();
     let mut score: i32 = 0;
-    if let Some(best_index) = best_substring_index(text, term) {
+    let substring = best_substring_index(text, term);
+    if let Some(best_index) = substring {
         score -= 200 + best_index as i32;
         if is_word_boundary(text, best_index) {
             score -= 30;
         }
-        let end = best_index + term.len();
-        if is_word_boundary(text, end) {
+        if is_word_boundary(text, best_index + term_len) {
             score -= 10;
         }
     }
 
     let mut last_pos: i32 = -1;
-    let mut start = 0usize;
-
-    for ch in term.chars() {
-        let slice = &text[start..];
-        let mut found = None;
-        for (pos, candidate) in slice.char_indices() {
-            if candidate == ch {
-                found = Some(start + pos);
-                break;
+    let mut wanted = term.chars().peekable();
+    for (index, ch) in text.chars().enumerate() {
+        let Some(&next) = wanted.peek() else {
+            break;
+        };
+        if ch != next {
+            continue;
+        }
+        score += index as i32 - last_pos;
+        last_pos = index as i32;
+        wanted.next();
+        if substring.is_none() {
+            if let Some(positions) = positions.as_deref_mut() {
+                positions.push(index);
             }
         }
-        let next_index = found?;
-        score += next_index as i32 - last_pos;
-        last_pos = next_index as i32;
-        start = next_index + ch.len_utf8();
+    }
+    if wanted.peek().is_some() {
+        return None;
+    }
+
+    if let (Some(best_index), Some(positions)) = (substring, positions) {
+        positions.extend(best_index..best_index + term_len);
     }
 
     Some(score)
 }
 
+fn highlight_group(prompt: &PromptEntry, doc: &IndexedDoc, group: &QueryGroup) -> Highlights {
+    let mut title = Vec::new();
+    let mut preview = Vec::new();
+    let mut tags = vec![Vec::new(); prompt.tags.len()];
+
+    for clause in &group.clauses {
+        match clause {
+            Clause::Tag {
+                tag,
+                negated: false,
+            } => {
+                // `#work` matches `work/email`; only the `work` prefix lights up.
+                for (index, candidate) in prompt.tags.iter().enumerate() {
+                    if tag_matches(candidate, tag) {
+                        tags[index].extend(0..tag.chars().count());
+                    }
+                }
+            }
+            Clause::Text {
+                field,
+                text,
+                negated: false,
+                ..
+            } => {
+                if matches!(field, QueryField::Any | QueryField::Title) {
+                    title.extend(
+                        title_char_positions(doc, text)
+                            .into_iter()
+                            .filter_map(|index| doc.title_map.get(index).copied()),
+                    );
+                }
+                if matches!(field, QueryField::Any | QueryField::Body) {
+                    preview.extend(
+                        char_positions(doc.preview(), text)
+                            .into_iter()
+                            .filter_map(|index| doc.preview_map.get(index).copied()),
+                    );
+                }
+                if *field == QueryField::Any {
+                    for (index, tag) in prompt.tags.iter().enumerate() {
+                        tags[index].extend(char_positions(tag, text));
+                    }
+                }
+            }
+            _ => {}
+        }
+    }
+
+    Highlights {
+        title: to_ranges(title),
+        preview: to_ranges(preview),
+        tags: tags.into_iter().map(to_ranges).collect(),
+    }
+}
+
+fn title_char_positions(doc: &IndexedDoc, term: &str) -> Vec<usize> {
+    let literal = char_positions(&doc.title, term);
+    if !literal.is_empty() || doc.title_pinyin.is_empty() {
+        return literal;
+    }
+    let full = score_match(&doc.title_pinyin, term);
+    let initials = score_match(&doc.title_initials, term);
+    match (full, initials) {
+        (Some(full), initials) if initials.is_none_or(|initials| full <= initials) => {
+            let mut positions: Vec<usize> = char_positions(&doc.title_pinyin, term)
+                .into_iter()
+                .filter_map(|index| doc.title_pinyin_map.get(index).copied())
+                .collect();
+            positions.dedup();
+            positions
+        }
+        // Initials hold exactly one character per title character.
+        (_, Some(_)) => char_positions(&doc.title_initials, term),
+        _ => Vec::new(),
+    }
+}
+
+/// Character indices of `term`'s match in `text`, empty when it does not match.
+fn char_positions(text: &str, term: &str) -> Vec<usize> {
+    let mut positions = Vec::new();
+    if match_term(text, term, Some(&mut positions)).is_none() {
+        return Vec::new();
+    }
+    positions
+}
+
+fn to_ranges(mut positions: Vec<usize>) -> Vec<HighlightRange> {
+    positions.sort_unstable();
+    positions.dedup();
+    let mut ranges: Vec<HighlightRange> = Vec::new();
+    for position in positions {
+        match ranges.last_mut() {
+            Some(last) if last.1 == position => last.1 += 1,
+            _ => ranges.push((position, position + 1)),
+        }
+    }
+    ranges
+}
+
+/// Character index of the first occurrence of `term` in `text`.
 fn best_substring_index(text: &str, term: &str) -> Option<usize> {
-    text.find(term)
+    text.find(term).map(|byte| text[..byte].chars().count())
 }
 
+/// Whether the character index `index` sits between a word and a non-word
+/// character (or at either end of `text`).
 fn is_word_boundary(text: &str, index: usize) -> bool {
-    if index == 0 || index >= text.len() {
+    if index == 0 {
         return true;
     }
-    let prev = text[..index].chars().last();
-    let next = text[index..].chars().next();
-    let prev_is_word = prev.map(|c| c.is_alphanumeric()).unwrap_or(false);
-    let next_is_word = next.map(|c| c.is_alphanumeric()).unwrap_or(false);
-    !prev_is_word || !next_is_word
+    let mut chars = text.chars().skip(index - 1);
+    let (Some(prev), Some(next)) = (chars.next(), chars.next()) else {
+        return true;
+    };
+    !prev.is_alphanumeric() || !next.is_alphanumeric()
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::frecency::FRECENCY_BOOST_MAX;
     use std::fs;
     use std::time::{SystemTime, UNIX_EPOCH};
 
+    fn entry(title: &str, body: &str, tags: &[&str]) -> PromptEntry {
+        PromptEntry {
+            id: title.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            preview: make_preview(body, 50),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            path: title.to_string(),
+            source: String::new(),
+        }
+    }
+
+    fn titles(results: &[PromptEntry]) -> Vec<&str> {
+        results.iter().map(|prompt| prompt.title.as_str()).collect()
+    }
+
     fn make_temp_dir(prefix: &str) -> std::path::PathBuf {
         let mut dir = std::env::temp_dir();
         let nanos = SystemTime::now()
@@ -352,30 +1295,34 @@ mod tests {
         dir
     }
 
+    fn root(dir: &Path) -> PromptRoot {
+        PromptRoot {
+            path: dir.to_string_lossy().to_string(),
+            label: "lib".to_string(),
+            enabled: true,
+        }
+    }
+
     #[test]
     fn normalize_tag_accepts_chinese_and_ascii() {
-        assert_eq!(normalize_tag("Tag1"), Some("tag1".to_string()));
-        assert_eq!(normalize_tag("标签1"), Some("标签1".to_string()));
+        let policy = default_policy();
+        assert_eq!(policy.normalize("Tag1"), Ok("tag1".to_string()));
+        assert_eq!(policy.normalize("标签1"), Ok("标签1".to_string()));
     }
 
     #[test]
     fn normalize_tag_rejects_special_chars() {
-        assert_eq!(normalize_tag("tag-1"), None);
-        assert_eq!(normalize_tag("tag_1"), None);
-        assert_eq!(normalize_tag("tag!"), None);
-        assert_eq!(normalize_tag("tag 1"), None);
+        let policy = default_policy();
+        assert!(policy.normalize("tag-1").is_err());
+        assert!(policy.normalize("tag_1").is_err());
+        assert!(policy.normalize("tag!").is_err());
+        assert!(policy.normalize("tag 1").is_err());
     }
 
     #[test]
     fn normalize_tag_rejects_overlong() {
-        assert_eq!(normalize_tag("12345678901"), None);
-    }
-
-    #[test]
-    fn split_query_filters_invalid_tags() {
-        let (tags, terms) = split_query("#Tag #tag-1 foo");
-        assert_eq!(tags, vec!["tag".to_string()]);
-        assert_eq!(terms, vec!["foo".to_string()]);
+        let policy = default_policy();
+        assert!(policy.normalize("12345678901").is_err());
     }
 
     #[test]
@@ -384,20 +1331,512 @@ mod tests {
         let path = dir.join("示例 #a #b.txt");
         fs::write(&path, "content").unwrap();
         let meta = TagsMeta::default();
-        let prompt = read_prompt(&path, &dir, &meta, 50).expect("read prompt");
+        let prompt =
+            read_prompt(&path, &dir, &meta, &TagRules::default(), 50).expect("read prompt");
 
         assert!(tags_match(&prompt, &["a".to_string()]));
         assert!(tags_match(&prompt, &["a".to_string(), "b".to_string()]));
         assert!(!tags_match(&prompt, &["a".to_string(), "c".to_string()]));
     }
 
+    #[test]
+    fn index_roots_applies_tag_rules() {
+        let dir = make_temp_dir("tag-rules");
+        let clients = dir.join("clients").join("acme");
+        fs::create_dir_all(&clients).unwrap();
+        fs::write(clients.join("brief.txt"), "plain").unwrap();
+        fs::write(dir.join("report.txt"), "SELECT * FROM t -- SQL").unwrap();
+        fs::write(
+            dir.join(".tag-rules.json"),
+            r#"{"rules":[
+                {"tags":["db"],"body_regex":"\\bsql\\b"},
+                {"tags":["client"],"path_glob":"clients/*"}
+            ]}"#,
+        )
+        .unwrap();
+
+        let mut prompts = index_roots(&[root(&dir)], default_policy(), 50);
+        prompts.sort_by(|a, b| a.title.cmp(&b.title));
+        assert_eq!(prompts[0].tags, vec!["client", "clients/acme"]);
+        assert_eq!(prompts[1].tags, vec!["db"]);
+    }
+
+    #[test]
+    fn index_cache_reuses_unchanged_files() {
+        let dir = make_temp_dir("index-cache");
+        let cache_path = dir.join("cache").join("index-cache.json");
+        let a = dir.join("a.txt");
+        fs::write(&a, "hello").unwrap();
+        fs::write(dir.join("b.txt"), "old").unwrap();
+        let mut cache = IndexCache::default();
+        assert_eq!(
+            index_roots_cached(&[root(&dir)], default_policy(), 50, &mut cache).len(),
+            2
+        );
+        assert!(cache.is_dirty());
+        cache.save(&cache_path).unwrap();
+
+        // Same size and mtime: the cached body is trusted over the file.
+        let modified = fs::metadata(&a).unwrap().modified().unwrap();
+        fs::write(&a, "HELLO").unwrap();
+        fs::File::options()
+            .write(true)
+            .open(&a)
+            .unwrap()
+            .set_modified(modified)
+            .unwrap();
+        let mut cache = IndexCache::load(&cache_path);
+        let prompts = index_roots_cached(&[root(&dir)], default_policy(), 50, &mut cache);
+        assert!(prompts.iter().any(|prompt| prompt.body == "hello"));
+        assert!(prompts.iter().all(|prompt| prompt.source == "lib"));
+        assert!(!cache.is_dirty());
+
+        fs::write(dir.join("b.txt"), "newer").unwrap();
+        fs::write(dir.join("c.txt"), "added").unwrap();
+        let mut prompts = index_roots_cached(&[root(&dir)], default_policy(), 50, &mut cache);
+        prompts.sort_by(|a, b| a.title.cmp(&b.title));
+        let bodies: Vec<&str> = prompts.iter().map(|prompt| prompt.body.as_str()).collect();
+        assert_eq!(bodies, vec!["hello", "newer", "added"]);
+        assert!(cache.is_dirty());
+
+        // Library metadata changes invalidate the whole root.
+        fs::write(dir.join(".tags.json"), r#"{"tags_by_path":{}}"#).unwrap();
+        let prompts = index_roots_cached(&[root(&dir)], default_policy(), 50, &mut cache);
+        assert!(prompts.iter().any(|prompt| prompt.body == "HELLO"));
+    }
+
+    #[test]
+    fn normalize_tag_accepts_nested_tags() {
+        let policy = default_policy();
+        assert_eq!(policy.normalize("Work/Email"), Ok("work/email".to_string()));
+        assert_eq!(
+            policy.normalize("工作/邮件/回复"),
+            Ok("工作/邮件/回复".to_string())
+        );
+        assert!(policy.normalize("work/").is_err());
+        assert!(policy.normalize("work//email").is_err());
+        assert!(policy.normalize("a/b/c/d/e/f").is_err());
+    }
+
+    #[test]
+    fn path_tags_keep_folder_hierarchy() {
+        let dir = make_temp_dir("path-tags");
+        let nested = dir.join("Work").join("Email");
+        let invalid = dir.join("Work").join("my-notes").join("Email");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir_all(&invalid).unwrap();
+        let path = nested.join("回复.txt");
+        fs::write(&path, "content").unwrap();
+        fs::write(invalid.join("a.txt"), "content").unwrap();
+        let read = |path: &Path| {
+            read_prompt(path, &dir, &TagsMeta::default(), &TagRules::default(), 50)
+                .expect("read prompt")
+        };
+        let prompt = read(&path);
+        assert_eq!(prompt.tags, vec!["work/email".to_string()]);
+        assert_eq!(read(&invalid.join("a.txt")).tags, vec!["work".to_string()]);
+
+        assert!(tags_match(&prompt, &["work".to_string()]));
+        assert!(tags_match(&prompt, &["work/email".to_string()]));
+        assert!(!tags_match(&prompt, &["email".to_string()]));
+        assert!(!tags_match(&prompt, &["wor".to_string()]));
+    }
+
+    #[test]
+    fn search_tag_filters_include_children() {
+        let prompts = vec![
+            entry("Reply", "", &["work/email"]),
+            entry("Shop", "", &["workshop"]),
+            entry("Plan", "", &["work"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(&prompts, &index, "#work", 10, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["Plan", "Reply"]);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "#work -#work/email",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Plan"]);
+    }
+
+    #[test]
+    fn search_prompts_ranks_title_before_body() {
+        let prompts = vec![
+            entry("Weekly summary", "write an email digest", &[]),
+            entry("Email reply", "reply politely", &["email"]),
+            entry("Translate", "translate to english", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(&prompts, &index, "email", 10, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["Email reply", "Weekly summary"]);
+    }
+
+    #[test]
+    fn search_prompts_filters_tags_and_allowed_ids() {
+        let prompts = vec![
+            entry("邮件回复", "请回复", &["email"]),
+            entry("邮件草稿", "草稿", &["draft"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "#email 邮件",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["邮件回复"]);
+
+        let allowed: HashSet<String> = ["邮件草稿".to_string()].into_iter().collect();
+        let options = SearchOptions {
+            allowed_ids: Some(&allowed),
+            ..Default::default()
+        };
+        let results = search_prompts(&prompts, &index, "邮件", 10, &options);
+        assert_eq!(titles(&results), vec!["邮件草稿"]);
+        let results = search_prompts(&prompts, &index, "", 10, &options);
+        assert_eq!(titles(&results), vec!["邮件草稿"]);
+    }
+
+    #[test]
+    fn search_prompts_breaks_ties_by_path() {
+        let prompts = vec![entry("b note", "", &["x"]), entry("a note", "", &["x"])];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(&prompts, &index, "#x", 1, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["a note"]);
+        let results = search_prompts(&prompts, &index, "", 2, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["a note", "b note"]);
+    }
+
+    #[test]
+    fn search_page_counts_all_matches() {
+        let prompts = vec![
+            entry("note c", "", &["work", "email"]),
+            entry("note a", "", &["work"]),
+            entry("note b", "", &["home"]),
+            entry("other", "", &["work"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let options = SearchOptions::default();
+        let first = search_page(&prompts, &index, "#work", 0, 2, &options);
+        let second = search_page(&prompts, &index, "#work", 2, 2, &options);
+        assert_eq!(first.total, 3);
+        assert_eq!(titles(&first.items), vec!["note a", "note c"]);
+        assert_eq!(titles(&second.items), vec!["other"]);
+        assert_eq!(second.offset, 2);
+        let counts: Vec<(&str, usize)> = first
+            .tag_counts
+            .iter()
+            .map(|item| (item.tag.as_str(), item.count))
+            .collect();
+        assert_eq!(counts, vec![("work", 3), ("email", 1)]);
+    }
+
+    #[test]
+    fn search_prompts_applies_boosts_within_tier() {
+        let prompts = vec![
+            entry("Email reply", "", &[]),
+            entry("Email draft", "", &[]),
+            entry("Summary", "email digest", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let boosts: HashMap<String, i32> = [
+            ("Email draft".to_string(), 50),
+            ("Summary".to_string(), 500),
+        ]
+        .into_iter()
+        .collect();
+        let options = SearchOptions {
+            boosts: Some(&boosts),
+            ..Default::default()
+        };
+        let results = search_prompts(&prompts, &index, "email", 10, &options);
+        assert_eq!(
+            titles(&results),
+            vec!["Email draft", "Email reply", "Summary"]
+        );
+    }
+
+    #[test]
+    fn tag_matches_rank_above_body_matches() {
+        let prompts = vec![
+            entry("B", "mail mail", &[]),
+            entry("A", "", &["alpha", "mymailbox"]),
+            entry("C", "unrelated", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let boosts = HashMap::from([("B".to_string(), FRECENCY_BOOST_MAX)]);
+        let options = SearchOptions {
+            boosts: Some(&boosts),
+            ..Default::default()
+        };
+        let results = search_prompts(&prompts, &index, "mail", 10, &options);
+        assert_eq!(titles(&results), vec!["A", "B"]);
+    }
+
+    #[test]
+    fn search_prompts_supports_negation_and_or() {
+        let prompts = vec![
+            entry("Email reply", "reply politely", &["email"]),
+            entry("Email draft", "rough draft", &["email", "draft"]),
+            entry("Summary", "short summary", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "email -#draft",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Email reply"]);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "email -rough",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Email reply"]);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "summary OR #draft",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Summary", "Email draft"]);
+    }
+
+    #[test]
+    fn search_prompts_supports_phrases_and_fields() {
+        let mut prompts = vec![
+            entry("Reply", "confirm the next step", &[]),
+            entry("Next", "one step at a time", &[]),
+        ];
+        prompts[1].path = "clients/next.txt".to_string();
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "\"next step\"",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Reply"]);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "title:next",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Next"]);
+        let results = search_prompts(&prompts, &index, "body:next", 10, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["Reply"]);
+        let results = search_prompts(
+            &prompts,
+            &index,
+            "path:clients",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(titles(&results), vec!["Next"]);
+    }
+
+    #[test]
+    fn search_prompts_matches_title_pinyin() {
+        let prompts = vec![
+            entry("周报", "本周工作", &[]),
+            entry("邮件回复", "请回复", &["email"]),
+            entry("Notes", "yjhf youjian", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(&prompts, &index, "yjhf", 10, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["邮件回复", "Notes"]);
+        let results = search_prompts(&prompts, &index, "youjian", 10, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["邮件回复", "Notes"]);
+        let results = search_prompts(&prompts, &index, "title:zb", 10, &SearchOptions::default());
+        assert_eq!(titles(&results), vec!["周报"]);
+    }
+
+    #[test]
+    fn search_hits_report_field_and_highlights() {
+        let prompts = vec![
+            entry("Email reply", "please reply by email", &["email", "work"]),
+            entry("Summary", "send an email digest", &[]),
+            entry("邮件回复", "请回复", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let hits = search_hits(
+            &prompts,
+            &index,
+            "email #work",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(hits.len(), 1);
+        assert_eq!(hits[0].matched_field, Some(MatchField::Title));
+        assert_eq!(hits[0].highlights.title, vec![(0, 5)]);
+        assert_eq!(hits[0].highlights.preview, vec![(16, 21)]);
+        assert_eq!(hits[0].highlights.tags, vec![vec![(0, 5)], vec![(0, 4)]]);
+
+        let nested = vec![entry("Plan", "", &["work/email"])];
+        let nested_index = SearchIndex::build(&nested);
+        let hits = search_hits(
+            &nested,
+            &nested_index,
+            "#work",
+            10,
+            &SearchOptions::default(),
+        );
+        assert_eq!(hits[0].highlights.tags, vec![vec![(0, 4)]]);
+
+        let hits = search_hits(&prompts, &index, "digest", 10, &SearchOptions::default());
+        assert_eq!(hits[0].matched_field, Some(MatchField::Content));
+        assert!(hits[0].highlights.title.is_empty());
+        assert_eq!(hits[0].highlights.preview, vec![(14, 20)]);
+
+        let hits = search_hits(&prompts, &index, "hf", 10, &SearchOptions::default());
+        assert_eq!(hits[0].prompt.title, "邮件回复");
+        assert_eq!(hits[0].highlights.title, vec![(2, 4)]);
+        let hits = search_hits(&prompts, &index, "youjian", 10, &SearchOptions::default());
+        assert_eq!(hits[0].highlights.title, vec![(0, 2)]);
+    }
+
+    #[test]
+    fn typo_matches_rank_after_strict_matches() {
+        let prompts = vec![
+            entry("Weekly report", "be brief", &[]),
+            entry("Email reply", "be polite", &[]),
+            entry("Emial archive", "misspelled on purpose", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let options = SearchOptions::default();
+        assert_eq!(
+            titles(&search_prompts(&prompts, &index, "emial", 10, &options)),
+            vec!["Emial archive", "Email reply"]
+        );
+        assert_eq!(
+            titles(&search_prompts(
+                &prompts,
+                &index,
+                "emal rpely",
+                10,
+                &options
+            )),
+            vec!["Email reply"]
+        );
+        // Short terms never fall back to edit distance.
+        assert!(search_prompts(&prompts, &index, "bx", 10, &options).is_empty());
+    }
+
+    #[test]
+    fn search_prompts_ranks_body_matches_by_bm25() {
+        let long_body = format!("deploy once {}", "filler text ".repeat(60));
+        let prompts = vec![
+            entry("Long guide", &long_body, &[]),
+            entry(
+                "Checklist",
+                "deploy steps, deploy checks, deploy rollback",
+                &[],
+            ),
+            entry("Deploy notes", "", &[]),
+            entry("Other", "nothing here", &["deploy"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let results = search_prompts(&prompts, &index, "deploy", 10, &SearchOptions::default());
+        assert_eq!(
+            titles(&results),
+            vec!["Deploy notes", "Other", "Checklist", "Long guide"]
+        );
+    }
+
+    #[test]
+    fn body_tier_stays_between_tag_and_fuzzy_scores() {
+        assert_eq!(body_tier_score(0.0, 1), -200);
+        assert!(body_tier_score(1000.0, 1) >= -299);
+        assert!(body_tier_score(3.0, 1) < body_tier_score(1.0, 1));
+        assert_eq!(body_tier_score(0.0, 2), -400);
+    }
+
+    #[test]
+    fn char_positions_use_char_offsets() {
+        assert_eq!(char_positions("中文测试", "测试"), vec![2, 3]);
+        assert_eq!(char_positions("a-b-c", "ac"), vec![0, 4]);
+        assert!(char_positions("abc", "x").is_empty());
+        assert_eq!(to_ranges(vec![4, 0, 1, 2]), vec![(0, 3), (4, 5)]);
+    }
+
     #[test]
     fn best_substring_index_handles_multibyte() {
-        assert_eq!(best_substring_index("中文测试", "文"), Some(3));
+        assert_eq!(best_substring_index("中文测试", "文"), Some(1));
     }
 
     #[test]
     fn score_match_handles_chinese() {
         assert!(score_match("中文测试", "测").is_some());
     }
+
+    #[test]
+    fn score_match_counts_chars_not_bytes() {
+        // Same visible offset, so CJK and ASCII text must score the same.
+        assert_eq!(score_match("中文测试", "测试"), score_match("abcd", "cd"));
+        assert_eq!(score_match("中a文b", "ab"), score_match("xayb", "ab"));
+        assert!(is_word_boundary("中文 测试", 3));
+        assert!(!is_word_boundary("中文测试", 2));
+    }
+
+    #[test]
+    fn normalize_text_folds_width_and_compatibility_forms() {
+        let policy = default_policy();
+        assert_eq!(normalize_text("ＡＢＣ１２３"), "abc123");
+        assert_eq!(normalize_text("ｶﾀｶﾅ"), "カタカナ");
+        assert_eq!(normalize_text("Ｅｍａｉｌ　回复"), "email 回复");
+        assert_eq!(normalize_with_map("ﬁx"), ("fix".to_string(), vec![0, 0, 1]));
+        assert_eq!(
+            policy.normalize("＃ＷＯＲＫ".trim_start_matches('＃')),
+            Ok("work".to_string())
+        );
+    }
+
+    #[test]
+    fn search_prompts_matches_across_character_widths() {
+        let prompts = vec![
+            entry("Ｅｍａｉｌ 模板", "", &[]),
+            entry("ｶﾀｶﾅ notes", "", &[]),
+            entry("Summary", "", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let options = SearchOptions::default();
+        assert_eq!(
+            titles(&search_prompts(&prompts, &index, "email", 10, &options)),
+            vec!["Ｅｍａｉｌ 模板"]
+        );
+        assert_eq!(
+            titles(&search_prompts(
+                &prompts,
+                &index,
+                "ＥＭＡＩＬ",
+                10,
+                &options
+            )),
+            vec!["Ｅｍａｉｌ 模板"]
+        );
+        assert_eq!(
+            titles(&search_prompts(&prompts, &index, "カタカナ", 10, &options)),
+            vec!["ｶﾀｶﾅ notes"]
+        );
+
+        // Highlights point at the original, unnormalized title characters.
+        let prompts = vec![entry("ﬁx Ｅｍａｉｌ", "", &[])];
+        let index = SearchIndex::build(&prompts);
+        let hits = search_hits(&prompts, &index, "email", 10, &options);
+        assert_eq!(hits[0].highlights.title, vec![(3, 8)]);
+        let hits = search_hits(&prompts, &index, "fix", 10, &options);
+        assert_eq!(hits[0].highlights.title, vec![(0, 2)]);
+    }
 }
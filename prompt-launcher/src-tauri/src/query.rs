@@ -0,0 +1,300 @@
+use std::collections::HashMap;
+
+use crate::prompts::normalize_text;
+use crate::tag_policy::TagPolicy;
+use crate::tags_meta::canonical_tag;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum QueryField {
+    Any,
+    Title,
+    Body,
+    Path,
+}
+
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum Clause {
+    Tag {
+        tag: String,
+        negated: bool,
+    },
+    Text {
+        field: QueryField,
+        text: String,
+        phrase: bool,
+        negated: bool,
+    },
+}
+
+/// Clauses that must all hold for a prompt to match.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QueryGroup {
+    pub clauses: Vec<Clause>,
+}
+
+impl QueryGroup {
+    pub fn positive_tags(&self) -> Vec<String> {
+        self.clauses
+            .iter()
+            .filter_map(|clause| match clause {
+                Clause::Tag {
+                    tag,
+                    negated: false,
+                } => Some(tag.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    /// Positive text clauses whose field is covered by the search index. Path
+    /// terms are left out because paths are not part of the indexed text.
+    pub fn index_terms(&self) -> Vec<String> {
+        self.clauses
+            .iter()
+            .filter_map(|clause| match clause {
+                Clause::Text {
+                    field,
+                    text,
+                    negated: false,
+                    ..
+                } if *field != QueryField::Path => Some(text.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+}
+
+/// Groups joined by `OR`; a prompt matches if any group matches.
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct Query {
+    pub groups: Vec<QueryGroup>,
+}
+
+impl Query {
+    pub fn is_empty(&self) -> bool {
+        self.groups.is_empty()
+    }
+
+    /// Rewrites tag clauses to their canonical tags.
+    pub fn resolve_aliases(&mut self, aliases: &HashMap<String, String>) {
+        for clause in self.groups.iter_mut().flat_map(|group| &mut group.clauses) {
+            if let Clause::Tag { tag, .. } = clause {
+                *tag = canonical_tag(aliases, tag);
+            }
+        }
+    }
+}
+
+/// Parses the launcher query language:
+///
+/// - `term` fuzzy term, `"exact phrase"` contiguous match
+/// - `#tag` / `tag:tag` tag filter
+/// - `title:`, `body:`, `path:` restrict a term or phrase to one field
+/// - `-` in front of any clause excludes matches
+/// - `OR` (uppercase) separates alternative groups, everything else is ANDed
+pub fn parse_query(raw: &str, policy: &TagPolicy) -> Query {
+    let mut groups = Vec::new();
+    let mut current = QueryGroup::default();
+    let mut chars = raw.chars().peekable();
+
+    loop {
+        while chars.peek().is_some_and(|ch| ch.is_whitespace()) {
+            chars.next();
+        }
+        if chars.peek().is_none() {
+            break;
+        }
+
+        let mut token = String::new();
+        let mut phrase = None;
+        while let Some(&ch) = chars.peek() {
+            if ch.is_whitespace() {
+                break;
+            }
+            chars.next();
+            if ch == '"' {
+                let mut quoted = String::new();
+                for next in chars.by_ref() {
+                    if next == '"' {
+                        break;
+                    }
+                    quoted.push(next);
+                }
+                phrase = Some(quoted);
+                break;
+            }
+            token.push(ch);
+        }
+
+        if phrase.is_none() && token == "OR" {
+            if !current.clauses.is_empty() {
+                groups.push(std::mem::take(&mut current));
+            }
+            continue;
+        }
+        if let Some(clause) = parse_clause(&token, phrase, policy) {
+            current.clauses.push(clause);
+        }
+    }
+
+    if !current.clauses.is_empty() {
+        groups.push(current);
+    }
+    Query { groups }
+}
+
+fn parse_clause(token: &str, phrase: Option<String>, policy: &TagPolicy) -> Option<Clause> {
+    if token == "-" && phrase.is_none() {
+        return None;
+    }
+    let (negated, token) = match token.strip_prefix('-') {
+        Some(rest) if !rest.is_empty() || phrase.is_some() => (true, rest),
+        _ => (false, token),
+    };
+
+    if let Some(tag) = token.strip_prefix('#') {
+        return tag_clause(tag, phrase.as_deref(), negated, policy);
+    }
+
+    let (field, text) = match token.split_once(':') {
+        Some(("tag", rest)) => return tag_clause(rest, phrase.as_deref(), negated, policy),
+        Some(("title", rest)) => (QueryField::Title, rest),
+        Some(("body", rest)) => (QueryField::Body, rest),
+        Some(("path", rest)) => (QueryField::Path, rest),
+        _ => (QueryField::Any, token),
+    };
+
+    let is_phrase = phrase.is_some();
+    let mut text = text.to_string();
+    if let Some(phrase) = phrase {
+        text.push_str(&phrase);
+    }
+    let text = normalize_text(&text);
+    if text.trim().is_empty() {
+        return None;
+    }
+    Some(Clause::Text {
+        field,
+        text,
+        phrase: is_phrase,
+        negated,
+    })
+}
+
+fn tag_clause(
+    tag: &str,
+    phrase: Option<&str>,
+    negated: bool,
+    policy: &TagPolicy,
+) -> Option<Clause> {
+    let raw = format!("{tag}{}", phrase.unwrap_or_default());
+    policy
+        .normalize(&raw)
+        .ok()
+        .map(|tag| Clause::Tag { tag, negated })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn parse(raw: &str) -> Query {
+        parse_query(raw, &TagPolicy::default())
+    }
+
+    fn text(field: QueryField, text: &str, phrase: bool, negated: bool) -> Clause {
+        Clause::Text {
+            field,
+            text: text.to_string(),
+            phrase,
+            negated,
+        }
+    }
+
+    fn tag(tag: &str, negated: bool) -> Clause {
+        Clause::Tag {
+            tag: tag.to_string(),
+            negated,
+        }
+    }
+
+    #[test]
+    fn parse_query_filters_invalid_tags() {
+        let query = parse("#Tag #tag-1 foo");
+        assert_eq!(query.groups.len(), 1);
+        assert_eq!(
+            query.groups[0].clauses,
+            vec![tag("tag", false), text(QueryField::Any, "foo", false, false)]
+        );
+    }
+
+    #[test]
+    fn parse_query_handles_phrases_and_negation() {
+        let query = parse("\"Exact Phrase\" -draft -#old -\"two words\"");
+        assert_eq!(
+            query.groups[0].clauses,
+            vec![
+                text(QueryField::Any, "exact phrase", true, false),
+                text(QueryField::Any, "draft", false, true),
+                tag("old", true),
+                text(QueryField::Any, "two words", true, true),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_query_handles_field_prefixes() {
+        let query = parse("title:Email body:\"next step\" path:clients tag:work");
+        assert_eq!(
+            query.groups[0].clauses,
+            vec![
+                text(QueryField::Title, "email", false, false),
+                text(QueryField::Body, "next step", true, false),
+                text(QueryField::Path, "clients", false, false),
+                tag("work", false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_query_splits_groups_on_or() {
+        let query = parse("OR email reply OR #draft or OR");
+        assert_eq!(query.groups.len(), 2);
+        assert_eq!(
+            query.groups[0].clauses,
+            vec![
+                text(QueryField::Any, "email", false, false),
+                text(QueryField::Any, "reply", false, false),
+            ]
+        );
+        assert_eq!(
+            query.groups[1].clauses,
+            vec![tag("draft", false), text(QueryField::Any, "or", false, false)]
+        );
+    }
+
+    #[test]
+    fn resolve_aliases_rewrites_tag_clauses() {
+        let aliases: HashMap<String, String> =
+            [("邮件".to_string(), "email".to_string())].into_iter().collect();
+        let mut query = parse("#邮件 -tag:邮件/草稿 邮件");
+        query.resolve_aliases(&aliases);
+        assert_eq!(
+            query.groups[0].clauses,
+            vec![
+                tag("email", false),
+                tag("email/草稿", true),
+                text(QueryField::Any, "邮件", false, false),
+            ]
+        );
+    }
+
+    #[test]
+    fn parse_query_ignores_empty_clauses() {
+        assert!(parse("  - title: \"\"  ").is_empty());
+        assert_eq!(
+            parse("http://x").groups[0].clauses,
+            vec![text(QueryField::Any, "http://x", false, false)]
+        );
+    }
+}
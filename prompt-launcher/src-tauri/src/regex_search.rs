@@ -0,0 +1,216 @@
+use regex::{Regex, RegexBuilder};
+use std::time::{Duration, Instant};
+
+use crate::prompts::{
+    HighlightRange, Highlights, MatchField, PromptEntry, SearchHit, SearchOptions,
+};
+
+pub const REGEX_PREFIX: &str = "re:";
+pub const REGEX_PATTERN_MAX_CHARS: usize = 500;
+// Compiled program and lazy DFA caps; the regex crate matches in linear time,
+// so these plus the scan budget bound the cost of a single search.
+const REGEX_SIZE_LIMIT: usize = 1 << 20;
+const REGEX_DFA_SIZE_LIMIT: usize = 2 << 20;
+const REGEX_TIME_BUDGET: Duration = Duration::from_millis(300);
+
+/// Returns the pattern when the query asks for regex mode, either through the
+/// `re:` prefix or because the caller forced it.
+pub fn regex_pattern(query: &str, forced: bool) -> Option<&str> {
+    let trimmed = query.trim();
+    match trimmed.strip_prefix(REGEX_PREFIX) {
+        Some(pattern) => Some(pattern.trim()),
+        None if forced => Some(trimmed),
+        None => None,
+    }
+}
+
+pub fn compile_regex(pattern: &str) -> Result<Regex, String> {
+    if pattern.is_empty() {
+        return Err("正则表达式不能为空".to_string());
+    }
+    if pattern.chars().count() > REGEX_PATTERN_MAX_CHARS {
+        return Err(format!(
+            "正则表达式过长（最多 {REGEX_PATTERN_MAX_CHARS} 个字符）"
+        ));
+    }
+    RegexBuilder::new(pattern)
+        .case_insensitive(true)
+        .size_limit(REGEX_SIZE_LIMIT)
+        .dfa_size_limit(REGEX_DFA_SIZE_LIMIT)
+        .build()
+        .map_err(|err| match err {
+            regex::Error::CompiledTooBig(_) => "正则表达式过于复杂，请简化后重试".to_string(),
+            regex::Error::Syntax(message) => format!("正则表达式无效：{message}"),
+            other => format!("正则表达式无效：{other}"),
+        })
+}
+
+/// Positions of the prompts whose title or body matches `pattern`: title
+/// matches first, then body matches, each ordered by boost and path.
+pub fn regex_matches(
+    prompts: &[PromptEntry],
+    pattern: &str,
+    options: &SearchOptions,
+) -> Result<Vec<usize>, String> {
+    ranked_matches(prompts, &compile_regex(pattern)?, options)
+}
+
+/// `regex_matches` with every match highlighted in the title and preview, the
+/// regex counterpart of `prompts::search_hits`.
+pub fn regex_hits(
+    prompts: &[PromptEntry],
+    pattern: &str,
+    limit: usize,
+    options: &SearchOptions,
+) -> Result<Vec<SearchHit>, String> {
+    let regex = compile_regex(pattern)?;
+    let ids = ranked_matches(prompts, &regex, options)?;
+    Ok(ids
+        .into_iter()
+        .take(limit)
+        .map(|id| {
+            let prompt = &prompts[id];
+            let title = match_ranges(&regex, &prompt.title);
+            let matched_field = if title.is_empty() {
+                MatchField::Body
+            } else {
+                MatchField::Title
+            };
+            SearchHit {
+                prompt: prompt.clone(),
+                score: 0,
+                matched_field: Some(matched_field),
+                highlights: Highlights {
+                    title,
+                    preview: match_ranges(&regex, &prompt.preview),
+                    tags: vec![Vec::new(); prompt.tags.len()],
+                },
+            }
+        })
+        .collect())
+}
+
+fn ranked_matches(
+    prompts: &[PromptEntry],
+    regex: &Regex,
+    options: &SearchOptions,
+) -> Result<Vec<usize>, String> {
+    let started = Instant::now();
+    let mut matches = Vec::new();
+
+    for (id, prompt) in prompts.iter().enumerate() {
+        if started.elapsed() > REGEX_TIME_BUDGET {
+            return Err("正则搜索超时，请缩小匹配范围".to_string());
+        }
+        if options
+            .allowed_ids
+            .is_some_and(|ids| !ids.contains(&prompt.id))
+        {
+            continue;
+        }
+        let tier = if regex.is_match(&prompt.title) {
+            0
+        } else if regex.is_match(&prompt.body) {
+            1
+        } else {
+            continue;
+        };
+        let boost = options
+            .boosts
+            .and_then(|boosts| boosts.get(&prompt.id))
+            .copied()
+            .unwrap_or(0);
+        matches.push((tier, -boost, id));
+    }
+
+    matches.sort_by(|a, b| {
+        (a.0, a.1)
+            .cmp(&(b.0, b.1))
+            .then_with(|| prompts[a.2].path.cmp(&prompts[b.2].path))
+    });
+    Ok(matches.into_iter().map(|(_, _, id)| id).collect())
+}
+
+/// Non-empty matches of `regex` in `text` as character ranges.
+fn match_ranges(regex: &Regex, text: &str) -> Vec<HighlightRange> {
+    regex
+        .find_iter(text)
+        .filter(|found| !found.is_empty())
+        .map(|found| {
+            let start = text[..found.start()].chars().count();
+            (start, start + found.as_str().chars().count())
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+
+    fn entry(title: &str, body: &str) -> PromptEntry {
+        PromptEntry {
+            id: title.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            preview: String::new(),
+            tags: Vec::new(),
+            path: title.to_string(),
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn regex_pattern_uses_prefix_or_flag() {
+        assert_eq!(
+            regex_pattern("re: \\{\\{.*\\}\\}", false),
+            Some("\\{\\{.*\\}\\}")
+        );
+        assert_eq!(regex_pattern("email", false), None);
+        assert_eq!(regex_pattern(" email ", true), Some("email"));
+    }
+
+    #[test]
+    fn regex_matches_titles_before_bodies() {
+        let prompts = vec![
+            entry("b template", "Hello {{name}}"),
+            entry("a plain", "no placeholders"),
+            entry("{{title}} card", ""),
+            entry("a template", "{{date}} report"),
+        ];
+        let ids = regex_matches(&prompts, r"\{\{\w+\}\}", &SearchOptions::default()).unwrap();
+        assert_eq!(ids, vec![2, 3, 0]);
+
+        let allowed: HashSet<String> = ["b template".to_string()].into_iter().collect();
+        let options = SearchOptions {
+            allowed_ids: Some(&allowed),
+            ..Default::default()
+        };
+        assert_eq!(
+            regex_matches(&prompts, "TEMPLATE", &options).unwrap(),
+            vec![0]
+        );
+    }
+
+    #[test]
+    fn regex_hits_highlight_character_ranges() {
+        let mut prompt = entry("周报 {{date}}", "Dear {{name}}, thanks");
+        prompt.preview = prompt.body.clone();
+        let hits = regex_hits(&[prompt], r"\{\{\w+\}\}", 10, &SearchOptions::default()).unwrap();
+        assert_eq!(hits[0].matched_field, Some(MatchField::Title));
+        assert_eq!(hits[0].highlights.title, vec![(3, 11)]);
+        assert_eq!(hits[0].highlights.preview, vec![(5, 13)]);
+    }
+
+    #[test]
+    fn compile_regex_reports_invalid_patterns() {
+        assert!(compile_regex("").unwrap_err().contains("不能为空"));
+        assert!(compile_regex("(unclosed").unwrap_err().contains("无效"));
+        assert!(compile_regex(r"\w{1000}{1000}")
+            .unwrap_err()
+            .contains("过于复杂"));
+        assert!(compile_regex(&"a".repeat(REGEX_PATTERN_MAX_CHARS + 1))
+            .unwrap_err()
+            .contains("过长"));
+    }
+}
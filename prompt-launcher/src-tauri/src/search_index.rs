@@ -0,0 +1,628 @@
+use pinyin::ToPinyin;
+use std::borrow::Borrow;
+use std::collections::HashMap;
+use std::hash::Hash;
+use std::sync::OnceLock;
+
+use crate::prompts::{normalize_text, normalize_with_map, tag_prefixes, PromptEntry};
+use crate::tag_suggest::TermVectors;
+
+/// Lowercased search fields of a prompt, computed once per index build instead
+/// of on every keystroke.
+#[derive(Debug, Clone)]
+pub struct IndexedDoc {
+    /// Normalized title; `title_map` gives the source character of each char.
+    pub title: String,
+    pub title_map: Vec<usize>,
+    pub tags: String,
+    pub path: String,
+    pub full: String,
+    /// Full pinyin spelling of the title, empty for titles without Chinese.
+    pub title_pinyin: String,
+    /// First letter of every syllable in `title_pinyin`, one per title character.
+    pub title_initials: String,
+    /// Title character index for each character of `title_pinyin`.
+    pub title_pinyin_map: Vec<usize>,
+    /// Source character in `PromptEntry.preview` of each char of `preview()`.
+    pub preview_map: Vec<usize>,
+    /// Length of the normalized body in characters.
+    pub body_len: usize,
+    preview_start: usize,
+    body_start: usize,
+    body_end: usize,
+}
+
+impl IndexedDoc {
+    pub fn from_prompt(prompt: &PromptEntry) -> Self {
+        let tags = normalize_text(&prompt.tags.join(" "));
+        let (title, title_map) = normalize_with_map(&prompt.title);
+        let (preview, preview_map) = normalize_with_map(&prompt.preview);
+        let mut full = format!("{title} ");
+        let preview_start = full.len();
+        full.push_str(&preview);
+        full.push(' ');
+        let body_start = full.len();
+        let body = normalize_text(&prompt.body);
+        let body_len = body.chars().count();
+        full.push_str(&body);
+        let body_end = full.len();
+        full.push(' ');
+        full.push_str(&tags);
+        let (title_pinyin, title_initials, title_pinyin_map) = to_pinyin(&title);
+        Self {
+            title,
+            title_map,
+            tags,
+            path: normalize_text(&prompt.path),
+            full,
+            title_pinyin,
+            title_initials,
+            title_pinyin_map,
+            preview_map,
+            body_len,
+            preview_start,
+            body_start,
+            body_end,
+        }
+    }
+
+    pub fn preview(&self) -> &str {
+        &self.full[self.preview_start..self.body_start - 1]
+    }
+
+    pub fn body(&self) -> &str {
+        &self.full[self.body_start..self.body_end]
+    }
+}
+
+/// Converts Chinese characters to pinyin, keeping other characters as they are:
+/// `邮件回复 #email` becomes (`youjianhuifu #email`, `yjhf #email`), plus the
+/// title character index of every pinyin character.
+fn to_pinyin(text: &str) -> (String, String, Vec<usize>) {
+    let mut full = String::new();
+    let mut initials = String::new();
+    let mut map = Vec::new();
+    let mut has_pinyin = false;
+    for (index, ch) in text.chars().enumerate() {
+        match ch.to_pinyin() {
+            Some(pinyin) => {
+                has_pinyin = true;
+                let plain = pinyin.plain();
+                full.push_str(plain);
+                initials.extend(plain.chars().next());
+                map.extend(plain.chars().map(|_| index));
+            }
+            None => {
+                full.push(ch);
+                initials.push(ch);
+                map.push(index);
+            }
+        }
+    }
+    if !has_pinyin {
+        return (String::new(), String::new(), Vec::new());
+    }
+    (full, initials, map)
+}
+
+fn words(text: &str) -> impl Iterator<Item = &str> {
+    text.split(|ch: char| !ch.is_alphanumeric())
+        .filter(|word| word.chars().nth(1).is_some())
+}
+
+/// Damerau-Levenshtein distance (adjacent transpositions count as one edit),
+/// or `None` once it is certain to exceed `max`.
+fn bounded_edit_distance(a: &[char], b: &[char], max: usize) -> Option<usize> {
+    if a.len().abs_diff(b.len()) > max {
+        return None;
+    }
+    let width = b.len() + 1;
+    let mut before: Vec<usize> = vec![0; width];
+    let mut previous: Vec<usize> = (0..width).collect();
+    let mut current: Vec<usize> = vec![0; width];
+    for i in 1..=a.len() {
+        current[0] = i;
+        let mut row_min = current[0];
+        for j in 1..=b.len() {
+            let cost = usize::from(a[i - 1] != b[j - 1]);
+            let mut value = (previous[j] + 1)
+                .min(current[j - 1] + 1)
+                .min(previous[j - 1] + cost);
+            if i > 1 && j > 1 && a[i - 1] == b[j - 2] && a[i - 2] == b[j - 1] {
+                value = value.min(before[j - 2] + 1);
+            }
+            current[j] = value;
+            row_min = row_min.min(value);
+        }
+        if row_min > max {
+            return None;
+        }
+        std::mem::swap(&mut before, &mut previous);
+        std::mem::swap(&mut previous, &mut current);
+    }
+    let distance = previous[b.len()];
+    (distance <= max).then_some(distance)
+}
+
+/// Keys one prompt is posted under, each listed once.
+struct DocKeys {
+    chars: Vec<char>,
+    /// Every tag and ancestor, so `#work` finds `work/email`.
+    tags: Vec<String>,
+    words: Vec<String>,
+}
+
+impl DocKeys {
+    fn new(prompt: &PromptEntry, doc: &IndexedDoc) -> Self {
+        let mut chars: Vec<char> = doc
+            .full
+            .chars()
+            .chain(doc.title_pinyin.chars())
+            .filter(|ch| !ch.is_whitespace())
+            .collect();
+        chars.sort_unstable();
+        chars.dedup();
+        let mut tags: Vec<String> = prompt
+            .tags
+            .iter()
+            .flat_map(|tag| tag_prefixes(tag))
+            .map(str::to_string)
+            .collect();
+        tags.sort();
+        tags.dedup();
+        let mut words: Vec<String> = words(&doc.full).map(str::to_string).collect();
+        words.sort();
+        words.dedup();
+        Self { chars, tags, words }
+    }
+}
+
+/// Adds `id` to a posting list, keeping it sorted.
+fn insert_id(list: &mut Vec<u32>, id: u32) {
+    if list.last().is_none_or(|last| *last < id) {
+        list.push(id);
+    } else if let Err(position) = list.binary_search(&id) {
+        list.insert(position, id);
+    }
+}
+
+fn remove_id<K, Q>(postings: &mut HashMap<K, Vec<u32>>, key: &Q, id: u32)
+where
+    K: Borrow<Q> + Hash + Eq,
+    Q: Hash + Eq + ?Sized,
+{
+    let Some(list) = postings.get_mut(key) else {
+        return;
+    };
+    if let Ok(position) = list.binary_search(&id) {
+        list.remove(position);
+    }
+    if list.is_empty() {
+        postings.remove(key);
+    }
+}
+
+/// Strings a tag can be completed from: the tag and every suffix starting at
+/// a `/` segment, each also spelled in pinyin and pinyin initials.
+#[derive(Debug)]
+struct TagKeys {
+    tag: String,
+    keys: Vec<String>,
+}
+
+impl TagKeys {
+    fn new(tag: &str) -> Self {
+        let mut keys = Vec::new();
+        let starts = std::iter::once(0).chain(tag.match_indices('/').map(|(end, _)| end + 1));
+        for start in starts {
+            let suffix = &tag[start..];
+            let (pinyin, initials, _) = to_pinyin(suffix);
+            keys.push(suffix.to_string());
+            if !pinyin.is_empty() {
+                keys.push(pinyin);
+                keys.push(initials);
+            }
+        }
+        Self {
+            tag: tag.to_string(),
+            keys,
+        }
+    }
+}
+
+/// Inverted index over the prompt list, aligned by position with the
+/// `Vec<PromptEntry>` it was built from.
+///
+/// Fuzzy scoring matches query characters as an in-order subsequence, so a
+/// prompt can only match a term if its text (or title pinyin) contains every
+/// character of the term. Posting lists per character therefore narrow the
+/// candidates without ever dropping a prompt the linear scan would have
+/// returned.
+#[derive(Debug, Default)]
+pub struct SearchIndex {
+    docs: Vec<IndexedDoc>,
+    char_postings: HashMap<char, Vec<u32>>,
+    tag_postings: HashMap<String, Vec<u32>>,
+    /// Every indexed tag and ancestor, sorted, for autocomplete.
+    tag_keys: Vec<TagKeys>,
+    /// Alphanumeric words of the searchable text, for typo-tolerant lookups.
+    word_postings: HashMap<String, Vec<u32>>,
+    body_len_sum: usize,
+    /// Built on the first tag suggestion and dropped with the index.
+    term_vectors: OnceLock<TermVectors>,
+}
+
+impl SearchIndex {
+    pub fn build(prompts: &[PromptEntry]) -> Self {
+        let mut index = Self {
+            docs: Vec::with_capacity(prompts.len()),
+            ..Self::default()
+        };
+        for (id, prompt) in prompts.iter().enumerate() {
+            let doc = IndexedDoc::from_prompt(prompt);
+            index.post(id as u32, &DocKeys::new(prompt, &doc));
+            index.body_len_sum += doc.body_len;
+            index.docs.push(doc);
+        }
+        let mut tag_keys: Vec<TagKeys> = index
+            .tag_postings
+            .keys()
+            .map(|tag| TagKeys::new(tag))
+            .collect();
+        tag_keys.sort_by(|a, b| a.tag.cmp(&b.tag));
+        index.tag_keys = tag_keys;
+        index
+    }
+
+    /// Indexes `prompt` as the next doc, mirroring `Vec::push` on the list.
+    pub fn push(&mut self, prompt: &PromptEntry) {
+        let id = self.docs.len() as u32;
+        let doc = IndexedDoc::from_prompt(prompt);
+        let keys = DocKeys::new(prompt, &doc);
+        self.post(id, &keys);
+        self.body_len_sum += doc.body_len;
+        self.docs.push(doc);
+        self.sync_tag_keys(&keys.tags);
+        self.term_vectors = OnceLock::new();
+    }
+
+    /// Re-indexes doc `id`, which was built from `old`, as `new`.
+    pub fn replace(&mut self, id: usize, old: &PromptEntry, new: &PromptEntry) {
+        let doc = IndexedDoc::from_prompt(new);
+        let old_keys = DocKeys::new(old, &self.docs[id]);
+        let new_keys = DocKeys::new(new, &doc);
+        self.unpost(id as u32, &old_keys);
+        self.post(id as u32, &new_keys);
+        self.body_len_sum = self.body_len_sum - self.docs[id].body_len + doc.body_len;
+        self.docs[id] = doc;
+        self.sync_tag_keys(old_keys.tags.iter().chain(&new_keys.tags));
+        self.term_vectors = OnceLock::new();
+    }
+
+    /// Drops doc `id`, built from `removed`, the way `Vec::swap_remove` does:
+    /// the last doc, built from `last`, takes over its id.
+    pub fn swap_remove(&mut self, id: usize, removed: &PromptEntry, last: &PromptEntry) {
+        let last_id = self.docs.len() - 1;
+        let removed_keys = DocKeys::new(removed, &self.docs[id]);
+        self.unpost(id as u32, &removed_keys);
+        if id != last_id {
+            let last_keys = DocKeys::new(last, &self.docs[last_id]);
+            self.unpost(last_id as u32, &last_keys);
+            self.post(id as u32, &last_keys);
+        }
+        let doc = self.docs.swap_remove(id);
+        self.body_len_sum -= doc.body_len;
+        self.sync_tag_keys(&removed_keys.tags);
+        self.term_vectors = OnceLock::new();
+    }
+
+    fn post(&mut self, id: u32, keys: &DocKeys) {
+        for ch in &keys.chars {
+            insert_id(self.char_postings.entry(*ch).or_default(), id);
+        }
+        for tag in &keys.tags {
+            insert_id(self.tag_postings.entry(tag.clone()).or_default(), id);
+        }
+        for word in &keys.words {
+            insert_id(self.word_postings.entry(word.clone()).or_default(), id);
+        }
+    }
+
+    /// Removes `id` from the postings of `keys`, dropping lists left empty.
+    fn unpost(&mut self, id: u32, keys: &DocKeys) {
+        for ch in &keys.chars {
+            remove_id(&mut self.char_postings, ch, id);
+        }
+        for tag in &keys.tags {
+            remove_id(&mut self.tag_postings, tag, id);
+        }
+        for word in &keys.words {
+            remove_id(&mut self.word_postings, word, id);
+        }
+    }
+
+    /// Adds or drops the autocomplete entries of `tags` to match `tag_postings`.
+    fn sync_tag_keys<'a>(&mut self, tags: impl IntoIterator<Item = &'a String>) {
+        for tag in tags {
+            let position = self.tag_keys.binary_search_by(|entry| entry.tag.cmp(tag));
+            match (position, self.tag_postings.contains_key(tag)) {
+                (Err(position), true) => self.tag_keys.insert(position, TagKeys::new(tag)),
+                (Ok(position), false) => {
+                    self.tag_keys.remove(position);
+                }
+                _ => {}
+            }
+        }
+    }
+
+    pub fn doc(&self, id: usize) -> Option<&IndexedDoc> {
+        self.docs.get(id)
+    }
+
+    /// TF-IDF vectors of the indexed prompts for tag suggestions.
+    pub fn term_vectors(&self) -> &TermVectors {
+        self.term_vectors
+            .get_or_init(|| TermVectors::build(&self.docs))
+    }
+
+    pub fn avg_body_len(&self) -> f64 {
+        if self.docs.is_empty() {
+            return 0.0;
+        }
+        self.body_len_sum as f64 / self.docs.len() as f64
+    }
+
+    /// BM25 inverse document frequency of `term` over prompt bodies.
+    pub fn body_idf(&self, term: &str) -> f64 {
+        let matching = self
+            .candidates(&[], &[term.to_string()])
+            .into_iter()
+            .filter(|id| self.docs[*id].body().contains(term))
+            .count() as f64;
+        let total = self.docs.len() as f64;
+        (1.0 + (total - matching + 0.5) / (matching + 0.5)).ln()
+    }
+
+    /// Indexed words within `max_edits` edits of `term`, with their distance.
+    pub fn similar_words(&self, term: &str, max_edits: usize) -> Vec<(&str, usize)> {
+        let term: Vec<char> = term.chars().collect();
+        let mut word_chars = Vec::new();
+        self.word_postings
+            .keys()
+            .filter_map(|word| {
+                word_chars.clear();
+                word_chars.extend(word.chars());
+                bounded_edit_distance(&term, &word_chars, max_edits)
+                    .map(|edits| (word.as_str(), edits))
+            })
+            .collect()
+    }
+
+    /// Prompts carrying `tag` or one of its children.
+    pub fn tag_docs(&self, tag: &str) -> &[u32] {
+        self.tag_postings
+            .get(tag)
+            .map(|list| list.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Indexed tags where `prefix` (already normalized) starts the tag, one
+    /// of its segments, or their pinyin or initials. An empty prefix matches
+    /// every tag.
+    pub fn tags_with_prefix<'a>(&'a self, prefix: &'a str) -> impl Iterator<Item = &'a str> {
+        self.tag_keys
+            .iter()
+            .filter(move |entry| entry.keys.iter().any(|key| key.starts_with(prefix)))
+            .map(|entry| entry.tag.as_str())
+    }
+
+    pub fn word_docs(&self, word: &str) -> &[u32] {
+        self.word_postings
+            .get(word)
+            .map(|list| list.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the ids of prompts that may match, in ascending order so ties keep
+    /// the original library order.
+    pub fn candidates(&self, tags: &[String], terms: &[String]) -> Vec<usize> {
+        let mut lists: Vec<&[u32]> = Vec::new();
+        for tag in tags {
+            match self.tag_postings.get(tag) {
+                Some(list) => lists.push(list),
+                None => return Vec::new(),
+            }
+        }
+
+        let mut chars: Vec<char> = terms
+            .iter()
+            .flat_map(|term| term.chars())
+            .filter(|ch| !ch.is_whitespace())
+            .collect();
+        chars.sort_unstable();
+        chars.dedup();
+        for ch in chars {
+            match self.char_postings.get(&ch) {
+                Some(list) => lists.push(list),
+                None => return Vec::new(),
+            }
+        }
+
+        if lists.is_empty() {
+            return (0..self.docs.len()).collect();
+        }
+        lists.sort_by_key(|list| list.len());
+        let mut result: Vec<u32> = lists[0].to_vec();
+        for list in &lists[1..] {
+            result.retain(|id| list.binary_search(id).is_ok());
+            if result.is_empty() {
+                break;
+            }
+        }
+        result.into_iter().map(|id| id as usize).collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(title: &str, body: &str, tags: &[&str]) -> PromptEntry {
+        PromptEntry {
+            id: title.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            preview: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            path: title.to_string(),
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn candidates_require_every_term_char() {
+        let prompts = vec![
+            entry("Email reply", "be polite", &[]),
+            entry("Summary", "short text", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        assert_eq!(index.candidates(&[], &["eml".to_string()]), vec![0]);
+        assert!(index.candidates(&[], &["zzz".to_string()]).is_empty());
+    }
+
+    #[test]
+    fn candidates_intersect_tags_and_terms() {
+        let prompts = vec![
+            entry("邮件回复", "请回复", &["email"]),
+            entry("邮件草稿", "草稿", &["draft"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        assert_eq!(
+            index.candidates(&["email".to_string()], &["邮件".to_string()]),
+            vec![0]
+        );
+        assert_eq!(index.candidates(&[], &["邮件".to_string()]), vec![0, 1]);
+        assert!(index.candidates(&["missing".to_string()], &[]).is_empty());
+    }
+
+    #[test]
+    fn candidates_match_parent_tags() {
+        let prompts = vec![
+            entry("a", "", &["work", "work/email"]),
+            entry("b", "", &["workshop"]),
+            entry("c", "", &["work/email/reply"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        assert_eq!(index.candidates(&["work".to_string()], &[]), vec![0, 2]);
+        assert_eq!(
+            index.candidates(&["work/email".to_string()], &[]),
+            vec![0, 2]
+        );
+    }
+
+    #[test]
+    fn build_precomputes_title_pinyin() {
+        let prompts = vec![entry("邮件回复 #Email", "", &[]), entry("Plain", "", &[])];
+        let index = SearchIndex::build(&prompts);
+        let doc = index.doc(0).unwrap();
+        assert_eq!(doc.title_pinyin, "youjianhuifu #email");
+        assert_eq!(doc.title_initials, "yjhf #email");
+        assert!(index.doc(1).unwrap().title_pinyin.is_empty());
+        assert_eq!(index.candidates(&[], &["yjhf".to_string()]), vec![0]);
+    }
+
+    #[test]
+    fn bounded_edit_distance_counts_transpositions() {
+        let chars = |text: &str| text.chars().collect::<Vec<_>>();
+        assert_eq!(
+            bounded_edit_distance(&chars("emial"), &chars("email"), 1),
+            Some(1)
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("emal"), &chars("email"), 1),
+            Some(1)
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("email"), &chars("email"), 0),
+            Some(0)
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("emial"), &chars("email"), 0),
+            None
+        );
+        assert_eq!(
+            bounded_edit_distance(&chars("summary"), &chars("email"), 2),
+            None
+        );
+    }
+
+    #[test]
+    fn similar_words_come_from_indexed_text() {
+        let prompts = vec![entry("Email reply", "be polite", &[])];
+        let index = SearchIndex::build(&prompts);
+        assert_eq!(index.similar_words("emial", 1), vec![("email", 1)]);
+        assert_eq!(index.word_docs("polite"), &[0]);
+        assert!(index.word_docs("missing").is_empty());
+    }
+
+    #[test]
+    fn body_idf_favors_rare_terms() {
+        let prompts = vec![
+            entry("a", "common rare", &[]),
+            entry("b", "common", &[]),
+            entry("c", "common", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        assert!(index.body_idf("rare") > index.body_idf("common"));
+        assert!(index.body_idf("missing") > index.body_idf("rare"));
+        assert!((index.avg_body_len() - 23.0 / 3.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn candidates_without_filters_returns_all() {
+        let prompts = vec![entry("a", "", &[]), entry("b", "", &[])];
+        let index = SearchIndex::build(&prompts);
+        assert_eq!(index.candidates(&[], &[]), vec![0, 1]);
+    }
+
+    #[test]
+    fn incremental_updates_match_a_rebuild() {
+        let mut prompts = vec![
+            entry("邮件回复", "please reply", &["work/email"]),
+            entry("Summary", "short text", &["work"]),
+            entry("Draft", "rough notes", &["draft"]),
+        ];
+        let mut index = SearchIndex::build(&prompts);
+
+        let edited = entry("Summary", "long text", &["report"]);
+        index.replace(1, &prompts[1], &edited);
+        prompts[1] = edited;
+        let added = entry("Poem", "spring", &["work/poem"]);
+        index.push(&added);
+        prompts.push(added);
+        index.swap_remove(0, &prompts[0], &prompts[3]);
+        prompts.swap_remove(0);
+
+        let rebuilt = SearchIndex::build(&prompts);
+        let tags = |index: &SearchIndex| -> Vec<String> {
+            index.tags_with_prefix("").map(str::to_string).collect()
+        };
+        assert_eq!(tags(&index), tags(&rebuilt));
+        assert_eq!(tags(&index), vec!["draft", "report", "work", "work/poem"]);
+        for tag in ["work", "work/email", "report", "draft"] {
+            assert_eq!(index.tag_docs(tag), rebuilt.tag_docs(tag), "{tag}");
+        }
+        for term in ["spring", "long", "reply", "邮件", "yj", "notes"] {
+            let terms = [term.to_string()];
+            assert_eq!(
+                index.candidates(&[], &terms),
+                rebuilt.candidates(&[], &terms)
+            );
+        }
+        for word in ["spring", "short", "long", "reply"] {
+            assert_eq!(index.word_docs(word), rebuilt.word_docs(word), "{word}");
+        }
+        assert_eq!(index.avg_body_len(), rebuilt.avg_body_len());
+        assert_eq!(index.doc(0).unwrap().title, "poem");
+    }
+}
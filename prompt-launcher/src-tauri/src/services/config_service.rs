@@ -1,4 +1,5 @@
-use crate::config::{save, AppConfig};
+use crate::config::{save, AppConfig, SavedSearch};
+use crate::regex_search::{compile_regex, regex_pattern};
 use crate::services::prompts_service::PromptsService;
 use crate::services::window_service::WindowService;
 use crate::state::{AppState, RecentState};
@@ -7,6 +8,8 @@ use std::sync::Arc;
 use std::time::{SystemTime, UNIX_EPOCH};
 use tauri::AppHandle;
 
+pub const FRECENCY_WEIGHT_MAX: u32 = 1000;
+
 pub struct ConfigService;
 
 impl ConfigService {
@@ -43,6 +46,12 @@ impl ConfigService {
         if trimmed.is_empty() {
             return Err("快捷键不能为空".to_string());
         }
+        {
+            let config = state.config.lock().unwrap();
+            if hotkey_taken_by_search(&config.saved_searches, trimmed, None) {
+                return Err("快捷键已被保存的搜索占用".to_string());
+            }
+        }
         WindowService::update_hotkey_registration(app, state, trimmed)?;
         let mut config = state.config.lock().unwrap();
         if config.hotkey == trimmed {
@@ -101,10 +110,12 @@ impl ConfigService {
             .as_millis() as i64;
         let current_id = config.recent_ids.first().cloned().unwrap_or_default();
         if !current_id.is_empty() {
+            *config.use_counts.entry(current_id.clone()).or_insert(0) += 1;
             config.recent_meta.insert(current_id, now);
         }
         let keep: HashSet<String> = config.recent_ids.iter().cloned().collect();
         config.recent_meta.retain(|key, _| keep.contains(key));
+        config.use_counts.retain(|key, _| keep.contains(key));
         save(app, &config)?;
         Ok(RecentState {
             recent_ids: config.recent_ids.clone(),
@@ -158,6 +169,28 @@ impl ConfigService {
         Ok(())
     }
 
+    pub fn set_frecency(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        weight: u32,
+        half_life_hours: u32,
+    ) -> Result<(), String> {
+        let mut config = state.config.lock().unwrap();
+        config.frecency_weight = weight.min(FRECENCY_WEIGHT_MAX);
+        config.frecency_half_life_hours = half_life_hours.max(1);
+        save(app, &config)
+    }
+
+    pub fn set_watch_quiet_ms(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        quiet_ms: u32,
+    ) -> Result<(), String> {
+        let mut config = state.config.lock().unwrap();
+        config.watch_quiet_ms = PromptsService::clamp_watch_quiet_ms(quiet_ms);
+        save(app, &config)
+    }
+
     pub fn set_show_shortcuts_hint(
         app: &AppHandle,
         state: &Arc<AppState>,
@@ -168,6 +201,80 @@ impl ConfigService {
         save(app, &config)
     }
 
+    pub fn list_saved_searches(state: &AppState) -> Vec<SavedSearch> {
+        state.config.lock().unwrap().saved_searches.clone()
+    }
+
+    pub fn create_saved_search(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        name: String,
+        query: String,
+        favorites_only: bool,
+        hotkey: Option<String>,
+        regex: bool,
+    ) -> Result<SavedSearch, String> {
+        let mut config = state.config.lock().unwrap();
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map_err(|e| format!("time error: {e}"))?
+            .as_millis();
+        let mut id = format!("search-{now}");
+        let mut suffix = 1;
+        while config.saved_searches.iter().any(|search| search.id == id) {
+            id = format!("search-{now}-{suffix}");
+            suffix += 1;
+        }
+        let search = validate_saved_search(
+            &config,
+            SavedSearch {
+                id,
+                name,
+                query,
+                favorites_only,
+                hotkey,
+                regex,
+            },
+        )?;
+        let mut searches = config.saved_searches.clone();
+        searches.push(search.clone());
+        store_saved_searches(app, state, &mut config, searches)?;
+        Ok(search)
+    }
+
+    pub fn update_saved_search(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        search: SavedSearch,
+    ) -> Result<SavedSearch, String> {
+        let mut config = state.config.lock().unwrap();
+        let pos = config
+            .saved_searches
+            .iter()
+            .position(|item| item.id == search.id)
+            .ok_or_else(|| "保存的搜索不存在".to_string())?;
+        let search = validate_saved_search(&config, search)?;
+        let mut searches = config.saved_searches.clone();
+        searches[pos] = search.clone();
+        store_saved_searches(app, state, &mut config, searches)?;
+        Ok(search)
+    }
+
+    pub fn delete_saved_search(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        id: String,
+    ) -> Result<Vec<SavedSearch>, String> {
+        let mut config = state.config.lock().unwrap();
+        let mut searches = config.saved_searches.clone();
+        searches.retain(|search| search.id != id);
+        if searches.len() == config.saved_searches.len() {
+            return Err("保存的搜索不存在".to_string());
+        }
+        store_saved_searches(app, state, &mut config, searches)?;
+        Ok(config.saved_searches.clone())
+    }
+
     pub fn clear_recent(
         app: &AppHandle,
         state: &Arc<AppState>,
@@ -175,6 +282,7 @@ impl ConfigService {
         let mut config = state.config.lock().unwrap();
         config.recent_ids.clear();
         config.recent_meta.clear();
+        config.use_counts.clear();
         save(app, &config)?;
         Ok(RecentState {
             recent_ids: config.recent_ids.clone(),
@@ -182,3 +290,105 @@ impl ConfigService {
         })
     }
 }
+
+/// Registers the hotkeys of `searches` and saves them. If any hotkey fails, the
+/// previous searches' hotkeys are registered again and nothing is saved.
+fn store_saved_searches(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    config: &mut AppConfig,
+    searches: Vec<SavedSearch>,
+) -> Result<(), String> {
+    if let Err(error) = WindowService::sync_saved_search_hotkeys(app, state, &searches) {
+        let _ = WindowService::sync_saved_search_hotkeys(app, state, &config.saved_searches);
+        return Err(error);
+    }
+    config.saved_searches = searches;
+    save(app, config)
+}
+
+/// Trims the user input and rejects empty names or queries, duplicate names and
+/// hotkeys that are already in use.
+fn validate_saved_search(config: &AppConfig, search: SavedSearch) -> Result<SavedSearch, String> {
+    let name = search.name.trim().to_string();
+    if name.is_empty() {
+        return Err("搜索名称不能为空".to_string());
+    }
+    let query = search.query.trim().to_string();
+    if query.is_empty() {
+        return Err("搜索内容不能为空".to_string());
+    }
+    if let Some(pattern) = regex_pattern(&query, search.regex) {
+        compile_regex(pattern)?;
+    }
+    if config
+        .saved_searches
+        .iter()
+        .any(|item| item.id != search.id && item.name == name)
+    {
+        return Err("已存在同名的保存搜索".to_string());
+    }
+    let hotkey = search
+        .hotkey
+        .map(|hotkey| hotkey.trim().to_string())
+        .filter(|hotkey| !hotkey.is_empty());
+    if let Some(hotkey) = hotkey.as_deref() {
+        if hotkey.eq_ignore_ascii_case(config.hotkey.trim())
+            || hotkey_taken_by_search(&config.saved_searches, hotkey, Some(&search.id))
+        {
+            return Err("快捷键已被占用".to_string());
+        }
+    }
+    Ok(SavedSearch {
+        id: search.id,
+        name,
+        query,
+        favorites_only: search.favorites_only,
+        hotkey,
+        regex: search.regex,
+    })
+}
+
+fn hotkey_taken_by_search(searches: &[SavedSearch], hotkey: &str, except_id: Option<&str>) -> bool {
+    searches.iter().any(|search| {
+        Some(search.id.as_str()) != except_id
+            && search
+                .hotkey
+                .as_deref()
+                .is_some_and(|current| current.eq_ignore_ascii_case(hotkey))
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn saved(id: &str, name: &str, hotkey: Option<&str>) -> SavedSearch {
+        SavedSearch {
+            id: id.to_string(),
+            name: name.to_string(),
+            query: "#work".to_string(),
+            favorites_only: false,
+            hotkey: hotkey.map(|value| value.to_string()),
+            regex: false,
+        }
+    }
+
+    #[test]
+    fn validate_saved_search_trims_and_rejects_conflicts() {
+        let config = AppConfig {
+            saved_searches: vec![saved("a", "Work", Some("Ctrl+Alt+W"))],
+            ..Default::default()
+        };
+
+        let search = validate_saved_search(&config, saved("b", "  Mail ", Some(" "))).unwrap();
+        assert_eq!(search.name, "Mail");
+        assert_eq!(search.hotkey, None);
+
+        assert!(validate_saved_search(&config, saved("b", "Work", None)).is_err());
+        assert!(validate_saved_search(&config, saved("b", "Mail", Some("ctrl+alt+w"))).is_err());
+        assert!(validate_saved_search(&config, saved("b", "Mail", Some("Alt+Space"))).is_err());
+        // Updating an entry may keep its own name and hotkey.
+        assert!(validate_saved_search(&config, saved("a", "Work", Some("Ctrl+Alt+W"))).is_ok());
+    }
+}
@@ -1,3 +1,4 @@
 pub mod config_service;
 pub mod prompts_service;
+pub mod tags_service;
 pub mod window_service;
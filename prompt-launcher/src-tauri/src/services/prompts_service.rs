@@ -1,26 +1,51 @@
-use crate::config::save;
-use crate::constants::EVENT_PROMPTS_UPDATED;
+use crate::config::{save, PromptRoot};
+use crate::constants::{EVENT_PROMPTS_CHANGED, EVENT_PROMPTS_UPDATED};
+use crate::frecency::frecency_boosts;
+use crate::index_cache::IndexCache;
 use crate::infrastructure::fs_prompt_file_repository::FsPromptFileRepository;
 use crate::prompts::{
-    index_prompts, make_preview, normalize_tag, search_prompts as search_prompts_impl, PromptEntry,
+    applied_tags, fallback_tags, has_prompt_files, index_roots, index_roots_cached, make_preview,
+    match_ids, page_from_ids, search_hits as search_hits_impl, search_page as search_page_impl,
+    search_prompts as search_prompts_impl, PromptEntry, RootIndexer, SearchHit, SearchOptions,
+    SearchPage,
+};
+use crate::regex_search::{regex_hits, regex_matches, regex_pattern};
+use crate::search_index::SearchIndex;
+use crate::state::{AppState, PromptsDelta};
+use crate::tag_complete::{complete_tags, TagCompletion};
+use crate::tag_policy::TagPolicy;
+use crate::tag_rules::{load_tag_rules, TagRules};
+use crate::tag_stats::{tag_stats, TagStats};
+use crate::tag_suggest::{suggest_tags, TagSuggestion};
+use crate::tags_meta::{
+    canonical_tag, load_library_meta, load_tags_meta, path_to_key, save_tags_meta,
+    touch_updated_at, TagsMeta,
 };
-use crate::state::AppState;
-use crate::tags_meta::{load_tags_meta, path_to_key, save_tags_meta, touch_updated_at};
 use crate::usecase::create_prompt_file::CreatePromptFileUseCase;
+use crate::watch_batch::WatchBatch;
 use notify::{RecursiveMode, Watcher};
+use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
 use std::fs;
 use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
 use std::sync::Arc;
-use std::time::{SystemTime, UNIX_EPOCH};
-use tauri::Emitter;
+use std::thread;
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
 use tauri::AppHandle;
+use tauri::Emitter;
 use tauri_plugin_opener::OpenerExt;
+use walkdir::WalkDir;
 
 pub const PREVIEW_CHARS_MIN: u32 = 10;
 pub const PREVIEW_CHARS_MAX: u32 = 200;
 // Grace period to keep newly created empty prompts hidden until editors finish saving.
 const PENDING_PROMPT_TTL_MS: u128 = 5_000;
+pub const WATCH_QUIET_MS_MIN: u32 = 50;
+pub const WATCH_QUIET_MS_MAX: u32 = 5_000;
+// A batch is flushed after this many quiet periods even while events keep
+// arriving, so a long copy still shows progress.
+const WATCH_MAX_BATCH_PERIODS: u32 = 10;
 
 pub struct PromptsService;
 
@@ -34,19 +59,181 @@ impl PromptsService {
         query: &str,
         limit: usize,
         favorites_only: bool,
-    ) -> Vec<PromptEntry> {
+        regex: bool,
+    ) -> Result<Vec<PromptEntry>, String> {
+        let (favorites, boosts) = search_signals(state, favorites_only);
+        let aliases = state.tag_aliases.read().unwrap();
+        let tag_policy = state.tag_policy.read().unwrap();
+        let options = SearchOptions {
+            allowed_ids: favorites.as_ref(),
+            boosts: Some(&boosts),
+            aliases: Some(&aliases),
+            tag_policy: Some(&tag_policy),
+        };
         let prompts = state.prompts.read().unwrap();
-        if favorites_only {
-            let favorites = state.config.lock().unwrap().favorites.clone();
-            let favorites: HashSet<String> = favorites.into_iter().collect();
-            let filtered: Vec<PromptEntry> = prompts
-                .iter()
-                .filter(|prompt| favorites.contains(&prompt.id))
-                .cloned()
-                .collect();
-            return search_prompts_impl(&filtered, query, limit);
+        if let Some(pattern) = regex_pattern(query, regex) {
+            let ids = regex_matches(&prompts, pattern, &options)?;
+            return Ok(ids
+                .into_iter()
+                .take(limit)
+                .map(|id| prompts[id].clone())
+                .collect());
+        }
+        let index = state.search_index.read().unwrap();
+        Ok(search_prompts_impl(
+            &prompts, &index, query, limit, &options,
+        ))
+    }
+
+    pub fn run_saved_search(
+        state: &AppState,
+        id: &str,
+        limit: usize,
+    ) -> Result<Vec<PromptEntry>, String> {
+        let search = state
+            .config
+            .lock()
+            .unwrap()
+            .saved_searches
+            .iter()
+            .find(|search| search.id == id)
+            .cloned()
+            .ok_or_else(|| "保存的搜索不存在".to_string())?;
+        Self::search(
+            state,
+            &search.query,
+            limit,
+            search.favorites_only,
+            search.regex,
+        )
+    }
+
+    pub fn search_page(
+        state: &AppState,
+        query: &str,
+        offset: usize,
+        limit: usize,
+        favorites_only: bool,
+        regex: bool,
+    ) -> Result<SearchPage, String> {
+        let (favorites, boosts) = search_signals(state, favorites_only);
+        let aliases = state.tag_aliases.read().unwrap();
+        let tag_policy = state.tag_policy.read().unwrap();
+        let options = SearchOptions {
+            allowed_ids: favorites.as_ref(),
+            boosts: Some(&boosts),
+            aliases: Some(&aliases),
+            tag_policy: Some(&tag_policy),
+        };
+        let prompts = state.prompts.read().unwrap();
+        if let Some(pattern) = regex_pattern(query, regex) {
+            let ids = regex_matches(&prompts, pattern, &options)?;
+            return Ok(page_from_ids(&prompts, &ids, offset, limit));
+        }
+        let index = state.search_index.read().unwrap();
+        Ok(search_page_impl(
+            &prompts, &index, query, offset, limit, &options,
+        ))
+    }
+
+    /// Tag counts, recency-weighted usage and co-occurring pairs over the whole
+    /// library (empty query) or the prompts matching `query`.
+    pub fn tag_stats(
+        state: &AppState,
+        query: &str,
+        favorites_only: bool,
+        regex: bool,
+        limit: usize,
+    ) -> Result<TagStats, String> {
+        let (favorites, boosts) = search_signals(state, favorites_only);
+        let aliases = state.tag_aliases.read().unwrap();
+        let tag_policy = state.tag_policy.read().unwrap();
+        let options = SearchOptions {
+            allowed_ids: favorites.as_ref(),
+            boosts: Some(&boosts),
+            aliases: Some(&aliases),
+            tag_policy: Some(&tag_policy),
+        };
+        let (recent_meta, half_life) = {
+            let config = state.config.lock().unwrap();
+            (config.recent_meta.clone(), config.frecency_half_life_hours)
+        };
+        let now = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .map(|value| value.as_millis() as i64)
+            .unwrap_or(0);
+
+        let prompts = state.prompts.read().unwrap();
+        let ids = match regex_pattern(query, regex) {
+            Some(pattern) => regex_matches(&prompts, pattern, &options)?,
+            None => {
+                let index = state.search_index.read().unwrap();
+                match_ids(&prompts, &index, query, &options)
+            }
+        };
+        Ok(tag_stats(
+            &prompts,
+            &ids,
+            &recent_meta,
+            half_life,
+            now,
+            limit,
+        ))
+    }
+
+    /// Tags completing a partial `#tag` token, cheap enough to call on every
+    /// keystroke.
+    pub fn complete_tags(state: &AppState, input: &str, limit: usize) -> Vec<TagCompletion> {
+        let (_, boosts) = search_signals(state, false);
+        let aliases = state.tag_aliases.read().unwrap();
+        let prompts = state.prompts.read().unwrap();
+        let index = state.search_index.read().unwrap();
+        complete_tags(&index, &prompts, input, &aliases, &boosts, limit)
+    }
+
+    /// Tag suggestions for the prompt at `path`, computed offline from the
+    /// indexed library. The term vectors are cached on the search index.
+    pub fn suggest_tags(
+        state: &AppState,
+        path: &str,
+        limit: usize,
+    ) -> Result<Vec<TagSuggestion>, String> {
+        let aliases = state.tag_aliases.read().unwrap();
+        let policy = state.tag_policy.read().unwrap();
+        let prompts = state.prompts.read().unwrap();
+        let index = state.search_index.read().unwrap();
+        let target = prompts
+            .iter()
+            .position(|prompt| prompt.path == path)
+            .ok_or_else(|| "未找到提示词".to_string())?;
+        let vectors = index.term_vectors();
+        Ok(suggest_tags(
+            &prompts, vectors, target, &policy, &aliases, limit,
+        ))
+    }
+
+    pub fn search_hits(
+        state: &AppState,
+        query: &str,
+        limit: usize,
+        favorites_only: bool,
+        regex: bool,
+    ) -> Result<Vec<SearchHit>, String> {
+        let (favorites, boosts) = search_signals(state, favorites_only);
+        let aliases = state.tag_aliases.read().unwrap();
+        let tag_policy = state.tag_policy.read().unwrap();
+        let options = SearchOptions {
+            allowed_ids: favorites.as_ref(),
+            boosts: Some(&boosts),
+            aliases: Some(&aliases),
+            tag_policy: Some(&tag_policy),
+        };
+        let prompts = state.prompts.read().unwrap();
+        if let Some(pattern) = regex_pattern(query, regex) {
+            return regex_hits(&prompts, pattern, limit, &options);
         }
-        search_prompts_impl(&prompts, query, limit)
+        let index = state.search_index.read().unwrap();
+        Ok(search_hits_impl(&prompts, &index, query, limit, &options))
     }
 
     pub fn set_prompts_dir(
@@ -68,20 +255,65 @@ impl PromptsService {
             pending.clear();
         }
 
-        let prompts = Self::refresh_prompts(state, &dir);
-        Self::start_watcher(app.clone(), state.clone(), dir)?;
+        let prompts = Self::refresh_prompts(state);
+        Self::start_watcher(app.clone(), state.clone())?;
         Ok(prompts)
     }
 
-    pub fn create_prompt_file(state: &Arc<AppState>, name: String) -> Result<String, String> {
-        let dir = {
-            let config = state.config.lock().unwrap();
-            config.prompts_dir.clone()
-        };
-        if dir.trim().is_empty() {
-            return Err("提示词目录未配置".to_string());
+    pub fn set_prompt_roots(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        roots: Vec<PromptRoot>,
+    ) -> Result<Vec<PromptEntry>, String> {
+        let mut next: Vec<PromptRoot> = Vec::new();
+        for root in roots {
+            let path = root.path.trim().to_string();
+            if path.is_empty() {
+                return Err("目录路径不能为空".to_string());
+            }
+            if root.enabled && !Path::new(&path).is_dir() {
+                return Err(format!("目录不存在：{path}"));
+            }
+            if next.iter().any(|existing| existing.path == path) {
+                continue;
+            }
+            next.push(PromptRoot {
+                path,
+                label: root.label.trim().to_string(),
+                enabled: root.enabled,
+            });
         }
-        let root = PathBuf::from(dir);
+
+        {
+            let mut config = state.config.lock().unwrap();
+            config.prompt_roots = next;
+            save(app, &config)?;
+        }
+
+        let prompts = Self::refresh_prompts(state);
+        Self::start_watcher(app.clone(), state.clone())?;
+        Ok(prompts)
+    }
+
+    /// Creates an empty prompt in `root`, which must be one of the enabled
+    /// roots; without it the file goes to the primary root.
+    pub fn create_prompt_file(
+        state: &Arc<AppState>,
+        name: String,
+        root: Option<String>,
+    ) -> Result<String, String> {
+        let root = match root.as_deref() {
+            Some(path) => enabled_root(state, path)?,
+            None => state
+                .config
+                .lock()
+                .unwrap()
+                .active_roots()
+                .into_iter()
+                .next()
+                .map(|root| PathBuf::from(root.path))
+                .ok_or_else(|| "提示词目录未配置".to_string())?,
+        };
         let usecase = CreatePromptFileUseCase::new(FsPromptFileRepository);
         let path = usecase.execute(&root, &name)?;
         let path_string = path.to_string_lossy().to_string();
@@ -97,13 +329,9 @@ impl PromptsService {
         Ok(path_string)
     }
 
-    pub fn open_prompt_path(
-        app: &AppHandle,
-        state: &AppState,
-        path: String,
-    ) -> Result<(), String> {
-        let root = resolve_prompts_root(state)?;
-        let target = resolve_prompt_path(&root, Path::new(&path))?;
+    pub fn open_prompt_path(app: &AppHandle, state: &AppState, path: String) -> Result<(), String> {
+        let roots = resolve_prompt_roots(state)?;
+        let (_, target) = resolve_prompt_path(&roots, Path::new(&path))?;
         let target_str = target.to_string_lossy().to_string();
         #[cfg(target_os = "windows")]
         {
@@ -138,19 +366,22 @@ impl PromptsService {
         if paths.is_empty() {
             return Err("未选择任何提示词".to_string());
         }
-        let root = resolve_prompts_root(state)?;
+        let roots = resolve_prompt_roots(state)?;
         let mut remove_ids = HashSet::new();
-        let mut remove_keys = Vec::new();
+        let mut remove_keys: HashMap<PathBuf, Vec<String>> = HashMap::new();
 
         for raw_path in &paths {
-            let target = resolve_prompt_path(&root, Path::new(raw_path))?;
+            let (root, target) = resolve_prompt_path(&roots, Path::new(raw_path))?;
             let target_str = target.to_string_lossy().to_string();
             if target.exists() {
                 fs::remove_file(&target).map_err(|e| format!("删除失败: {e}"))?;
             }
             remove_ids.insert(raw_path.clone());
             remove_ids.insert(target_str.clone());
-            remove_keys.push(path_to_key(&root, &target));
+            remove_keys
+                .entry(root.clone())
+                .or_default()
+                .push(path_to_key(root, &target));
         }
 
         {
@@ -162,16 +393,21 @@ impl PromptsService {
             let mut config = state.config.lock().unwrap();
             config.favorites.retain(|item| !remove_ids.contains(item));
             config.recent_ids.retain(|item| !remove_ids.contains(item));
-            config.recent_meta.retain(|key, _| !remove_ids.contains(key));
+            config
+                .recent_meta
+                .retain(|key, _| !remove_ids.contains(key));
+            config.use_counts.retain(|key, _| !remove_ids.contains(key));
             save(app, &config)?;
         }
 
-        if let Ok(mut meta) = load_tags_meta(&root) {
+        for (root, keys) in remove_keys {
+            let Ok(mut meta) = load_tags_meta(&root) else {
+                continue;
+            };
             let mut changed = false;
-            for key in remove_keys {
-                if meta.tags_by_path.remove(&key).is_some() {
-                    changed = true;
-                }
+            for key in keys {
+                changed |= meta.tags_by_path.remove(&key).is_some();
+                changed |= meta.removed_by_path.remove(&key).is_some();
             }
             if changed {
                 touch_updated_at(&mut meta);
@@ -179,7 +415,7 @@ impl PromptsService {
             }
         }
 
-        Ok(Self::refresh_prompts(state, &root))
+        Ok(Self::refresh_prompts(state))
     }
 
     pub fn update_prompt_tags(
@@ -191,85 +427,290 @@ impl PromptsService {
         if paths.is_empty() {
             return Err("未选择任何提示词".to_string());
         }
-        let add_tags = normalize_input_tags(add)?;
-        let remove_tags = normalize_input_tags(remove)?;
+        let policy = state.tag_policy.read().unwrap().clone();
+        let add_tags = normalize_input_tags(add, &policy)?;
+        let remove_tags = normalize_input_tags(remove, &policy)?;
         if add_tags.is_empty() && remove_tags.is_empty() {
             return Err("标签不能为空".to_string());
         }
-        let dir = {
+        let roots: Vec<PathBuf> = {
             let config = state.config.lock().unwrap();
-            config.prompts_dir.clone()
+            config
+                .active_roots()
+                .into_iter()
+                .map(|root| PathBuf::from(root.path))
+                .collect()
         };
-        if dir.trim().is_empty() {
+        if roots.is_empty() {
             return Err("提示词目录未配置".to_string());
         }
-        let root = PathBuf::from(&dir);
-        let mut meta = load_tags_meta(&root)?;
-        let prompt_map = build_prompt_tag_map(state);
+        let mut metas: HashMap<PathBuf, TagsMeta> = HashMap::new();
+        let mut rules: HashMap<PathBuf, TagRules> = HashMap::new();
 
         for raw_path in paths {
             let path = PathBuf::from(&raw_path);
-            let key = path_to_key(&root, &path);
-            let base_tags = if let Some(existing) = meta.tags_by_path.get(&key) {
-                existing.clone()
-            } else {
-                prompt_map.get(&raw_path).cloned().unwrap_or_default()
+            let root =
+                root_for_path(&roots, &path).ok_or_else(|| "路径不在提示词目录中".to_string())?;
+            let meta = match metas.entry(root.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry.insert(load_tags_meta(root)?),
             };
-            let mut next = HashSet::new();
-            for tag in base_tags {
-                if let Some(normalized) = normalize_tag(&tag) {
-                    next.insert(normalized);
-                }
+            let root_rules = match rules.entry(root.clone()) {
+                Entry::Occupied(entry) => entry.into_mut(),
+                Entry::Vacant(entry) => entry
+                    .insert(load_tag_rules(root).and_then(|file| TagRules::compile(&file.rules))?),
+            };
+            let key = path_to_key(root, &path);
+            let body = fs::read_to_string(&path).unwrap_or_default();
+            let applied = applied_tags(&path, root, meta, root_rules, &policy, &body);
+            // Folder and rule tags stay where they come from: the override
+            // only holds the prompt's own tags, and removing an applied tag
+            // records it in `removed_by_path` instead.
+            let own_tags = match meta.tags_by_path.get(&key) {
+                Some(existing) => existing.clone(),
+                None => fallback_tags(&path, root, meta, &policy),
+            };
+            let mut own: HashSet<String> = own_tags
+                .iter()
+                .filter_map(|tag| policy.normalize(tag).ok())
+                .map(|tag| canonical_tag(&meta.aliases, &tag))
+                .collect();
+            let mut removed: HashSet<String> = meta
+                .removed_by_path
+                .get(&key)
+                .into_iter()
+                .flatten()
+                .cloned()
+                .collect();
+            for tag in canonical_tags(&add_tags, &meta.aliases) {
+                removed.remove(&tag);
+                own.insert(tag);
             }
-            for tag in &add_tags {
-                next.insert(tag.clone());
+            for tag in canonical_tags(&remove_tags, &meta.aliases) {
+                own.remove(&tag);
+                if applied.contains(&tag) {
+                    removed.insert(tag);
+                }
             }
-            for tag in &remove_tags {
-                next.remove(tag);
+            let mut own: Vec<String> = own.into_iter().collect();
+            own.sort();
+            meta.tags_by_path.insert(key.clone(), own);
+            if removed.is_empty() {
+                meta.removed_by_path.remove(&key);
+            } else {
+                let mut removed: Vec<String> = removed.into_iter().collect();
+                removed.sort();
+                meta.removed_by_path.insert(key, removed);
             }
-            let mut next_vec: Vec<String> = next.into_iter().collect();
-            next_vec.sort();
-            meta.tags_by_path.insert(key, next_vec);
         }
 
-        touch_updated_at(&mut meta);
-        save_tags_meta(&root, &meta)?;
-        Ok(Self::refresh_prompts(state, &root))
+        for (root, mut meta) in metas {
+            touch_updated_at(&mut meta);
+            save_tags_meta(&root, &meta)?;
+        }
+        Ok(Self::refresh_prompts(state))
+    }
+
+    /// Replaces the tags every prompt under `folder` inherits. An empty list
+    /// removes the folder entry.
+    pub fn set_folder_tags(
+        state: &Arc<AppState>,
+        folder: String,
+        tags: Vec<String>,
+    ) -> Result<Vec<PromptEntry>, String> {
+        let policy = state.tag_policy.read().unwrap().clone();
+        let tags = normalize_input_tags(tags, &policy)?;
+        let roots = resolve_prompt_roots(state)?;
+        let (root, target) = resolve_prompt_path(&roots, Path::new(&folder))?;
+        if !target.is_dir() || &target == root {
+            return Err("请选择提示词目录下的子文件夹".to_string());
+        }
+        let key = format!("{}/", path_to_key(root, &target));
+        let mut meta = load_tags_meta(root)?;
+        let tags = canonical_tags(&tags, &meta.aliases);
+        let changed = if tags.is_empty() {
+            meta.tags_by_path.remove(&key).is_some()
+        } else {
+            meta.tags_by_path.insert(key, tags.clone()).as_ref() != Some(&tags)
+        };
+        if changed {
+            touch_updated_at(&mut meta);
+            save_tags_meta(root, &meta)?;
+        }
+        Ok(Self::refresh_prompts(state))
     }
 
-    pub fn refresh_prompts(state: &Arc<AppState>, dir: &Path) -> Vec<PromptEntry> {
-        let preview_chars = {
+    /// Re-indexes every enabled prompt root, reusing unchanged entries from
+    /// the index cache and saving it when the pass changed it.
+    pub fn refresh_prompts(state: &Arc<AppState>) -> Vec<PromptEntry> {
+        let _pass = state.index_pass.lock().unwrap();
+        let (roots, preview_chars) = {
             let config = state.config.lock().unwrap();
-            Self::clamp_preview_chars(config.preview_chars) as usize
+            (
+                config.active_roots(),
+                Self::clamp_preview_chars(config.preview_chars) as usize,
+            )
         };
-        let prompts = index_prompts(dir, preview_chars);
-        let pending = { state.pending_paths.lock().unwrap().clone() };
-        let mut next_pending = HashMap::new();
-        let mut visible = Vec::new();
-        let now = SystemTime::now()
-            .duration_since(UNIX_EPOCH)
-            .map(|value| value.as_millis())
-            .unwrap_or(0);
+        let library = load_library_meta(&root_paths(&roots));
+        let prompts = match &mut *state.index_cache.lock().unwrap() {
+            Some((path, cache)) => {
+                let prompts = index_roots_cached(&roots, &library.policy, preview_chars, cache);
+                save_index_cache(path, cache);
+                prompts
+            }
+            None => index_roots(&roots, &library.policy, preview_chars),
+        };
+        set_library_meta(state, library);
+        let visible = hide_pending(state, prompts);
+        Self::replace_prompts(state, visible.clone());
+        visible
+    }
+
+    /// Shows the entries saved in the index cache without reading the prompt
+    /// files, so the launcher is usable at startup before `refresh_prompts`
+    /// has validated them.
+    pub fn load_cached_prompts(state: &Arc<AppState>) {
+        let roots = state.config.lock().unwrap().active_roots();
+        let prompts = match &*state.index_cache.lock().unwrap() {
+            Some((_, cache)) => cache.prompts(&roots),
+            None => return,
+        };
+        set_library_meta(state, load_library_meta(&root_paths(&roots)));
+        Self::replace_prompts(state, prompts);
+    }
+
+    /// The startup work that reads the prompt files, run off the main thread
+    /// once the cached list is shown: seeds an empty library, starts the
+    /// watcher and re-validates the cached entries.
+    pub fn finish_startup(app: AppHandle, state: Arc<AppState>, dir: PathBuf) {
+        if let Err(error) = Self::seed_prompts_if_empty(&dir) {
+            eprintln!("[seed] {error}");
+        }
+        if let Err(error) = Self::start_watcher(app.clone(), state.clone()) {
+            eprintln!("[watcher] {error}");
+        }
+        let previous = state.prompts.read().unwrap().clone();
+        let prompts = Self::refresh_prompts(&state);
+        if prompts != previous {
+            let _ = app.emit(EVENT_PROMPTS_UPDATED, prompts);
+        }
+    }
 
-        for prompt in prompts {
-            if let Some(created_at) = pending.get(&prompt.id) {
-                let size = fs::metadata(&prompt.path).map(|m| m.len()).unwrap_or(0);
-                let age = now.saturating_sub(*created_at);
-                if size == 0 && age < PENDING_PROMPT_TTL_MS {
-                    next_pending.insert(prompt.id.clone(), *created_at);
-                    continue;
+    /// Re-reads only the prompts at or below `paths`, as reported by the file
+    /// watcher: existing files are updated or inserted, directories are walked,
+    /// and entries whose path is gone are dropped. Event paths are matched
+    /// against the roots after resolving symlinks. Runs under `index_pass`
+    /// like `refresh_prompts`, so the two apply in the order they read the
+    /// files; searches only wait while the list and its index are patched. A
+    /// dropped entry's slot goes to the last entry, like `Vec::swap_remove`.
+    /// The index cache is updated and saved afterwards. Returns `None` when
+    /// nothing changed.
+    pub fn reindex_paths(state: &Arc<AppState>, paths: &[PathBuf]) -> Option<PromptsDelta> {
+        let _pass = state.index_pass.lock().unwrap();
+        let (roots, preview_chars) = {
+            let config = state.config.lock().unwrap();
+            (
+                config.active_roots(),
+                Self::clamp_preview_chars(config.preview_chars) as usize,
+            )
+        };
+        let policy = state.tag_policy.read().unwrap().clone();
+        let indexers: Vec<RootIndexer> = roots
+            .iter()
+            .map(|root| RootIndexer::new(root, &policy))
+            .collect();
+        let mut fresh = Vec::new();
+        let mut seen = HashSet::new();
+        let mut resolved = Vec::new();
+        for path in paths {
+            // Like `index_roots`, a file under nested roots belongs to the
+            // first root listed.
+            let Some((indexer, path)) = indexers
+                .iter()
+                .find_map(|indexer| Some((indexer, indexer.resolve(path)?)))
+            else {
+                continue;
+            };
+            for prompt in read_path(indexer, &path, preview_chars) {
+                if seen.insert(prompt.id.clone()) {
+                    fresh.push(prompt);
                 }
             }
-            visible.push(prompt);
+            resolved.push(path);
         }
+        let fresh = hide_pending_fresh(state, fresh);
+        let delta = Self::patch_prompts(state, &resolved, fresh.clone());
 
-        if !pending.is_empty() {
-            *state.pending_paths.lock().unwrap() = next_pending;
+        if let Some((path, cache)) = &mut *state.index_cache.lock().unwrap() {
+            for prompt in &fresh {
+                cache.upsert(prompt);
+            }
+            for id in delta.iter().flat_map(|delta| &delta.removed) {
+                cache.remove(id);
+            }
+            save_index_cache(path, cache);
         }
+        delta
+    }
 
-        let mut lock = state.prompts.write().unwrap();
-        *lock = visible.clone();
-        visible
+    /// Applies a `reindex_paths` read under the list and index write locks.
+    fn patch_prompts(
+        state: &AppState,
+        paths: &[PathBuf],
+        fresh: Vec<PromptEntry>,
+    ) -> Option<PromptsDelta> {
+        let fresh_ids: HashSet<&str> = fresh.iter().map(|prompt| prompt.id.as_str()).collect();
+        let mut prompts = state.prompts.write().unwrap();
+        let mut index = state.search_index.write().unwrap();
+        let mut removed = Vec::new();
+        // Walk backwards so the entry `swap_remove` moves in was already seen.
+        for position in (0..prompts.len()).rev() {
+            let prompt = &prompts[position];
+            let gone = !fresh_ids.contains(prompt.id.as_str())
+                && paths
+                    .iter()
+                    .any(|path| Path::new(&prompt.path).starts_with(path));
+            if gone {
+                index.swap_remove(position, prompt, &prompts[prompts.len() - 1]);
+                removed.push(prompts.swap_remove(position).id);
+            }
+        }
+
+        let positions: HashMap<String, usize> = prompts
+            .iter()
+            .enumerate()
+            .map(|(position, prompt)| (prompt.id.clone(), position))
+            .collect();
+        let mut upserted = Vec::new();
+        for prompt in fresh {
+            match positions.get(&prompt.id) {
+                Some(&position) if prompts[position] == prompt => continue,
+                Some(&position) => {
+                    index.replace(position, &prompts[position], &prompt);
+                    prompts[position] = prompt.clone();
+                }
+                None => {
+                    index.push(&prompt);
+                    prompts.push(prompt.clone());
+                }
+            }
+            upserted.push(prompt);
+        }
+
+        if upserted.is_empty() && removed.is_empty() {
+            return None;
+        }
+        Some(PromptsDelta { upserted, removed })
+    }
+
+    /// Swaps in a new prompt list together with its search index. Both locks are
+    /// held while swapping so readers never see an index built for another list.
+    pub fn replace_prompts(state: &AppState, prompts: Vec<PromptEntry>) {
+        let index = SearchIndex::build(&prompts);
+        let mut prompts_lock = state.prompts.write().unwrap();
+        let mut index_lock = state.search_index.write().unwrap();
+        *prompts_lock = prompts;
+        *index_lock = index;
     }
 
     pub fn apply_preview_chars(state: &Arc<AppState>, preview_chars: u32) {
@@ -278,13 +719,15 @@ impl PromptsService {
         for prompt in prompts.iter_mut() {
             prompt.preview = make_preview(&prompt.body, preview_chars);
         }
+        let index = SearchIndex::build(&prompts);
+        *state.search_index.write().unwrap() = index;
     }
 
-    pub fn seed_prompts_if_empty(dir: &Path, preview_chars: usize) -> Result<(), String> {
+    pub fn seed_prompts_if_empty(dir: &Path) -> Result<(), String> {
         if !dir.exists() {
             return Ok(());
         }
-        if !index_prompts(dir, preview_chars).is_empty() {
+        if has_prompt_files(dir) {
             return Ok(());
         }
 
@@ -314,68 +757,256 @@ impl PromptsService {
         Ok(())
     }
 
-    pub fn start_watcher(
-        app: AppHandle,
-        state: Arc<AppState>,
-        dir: PathBuf,
-    ) -> Result<(), String> {
-        let index_dir = dir.clone();
-        let app_handle = app.clone();
-        let state_handle = state.clone();
-        let mut watcher = notify::recommended_watcher(
-            move |res: notify::Result<notify::Event>| {
-                if res.is_err() {
-                    return;
-                }
-                let prompts = Self::refresh_prompts(&state_handle, &index_dir);
-                let _ = app_handle.emit(EVENT_PROMPTS_UPDATED, prompts);
-            },
-        )
+    /// Watches every enabled root with a single watcher, replacing the previous
+    /// one. Roots that cannot be watched are skipped with a log line.
+    pub fn start_watcher(app: AppHandle, state: Arc<AppState>) -> Result<(), String> {
+        let roots = state.config.lock().unwrap().active_roots();
+        let (sender, receiver) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(move |res: notify::Result<notify::Event>| {
+            let _ = sender.send(res);
+        })
         .map_err(|e| format!("watcher init failed: {e}"))?;
 
-        watcher
-            .watch(&dir, RecursiveMode::Recursive)
-            .map_err(|e| format!("watcher start failed: {e}"))?;
+        for root in &roots {
+            if let Err(err) = watcher.watch(Path::new(&root.path), RecursiveMode::Recursive) {
+                eprintln!("[watcher] watch {} failed: {err}", root.path);
+            }
+        }
 
+        let worker_state = state.clone();
+        thread::spawn(move || Self::run_watch_worker(app, worker_state, receiver));
         *state.watcher.lock().unwrap() = Some(watcher);
         Ok(())
     }
 
+    /// Coalesces watcher events until no new one arrives for the configured
+    /// quiet period, then updates the library and emits once per batch. The
+    /// loop ends when the watcher, which owns the sender, is dropped; the
+    /// events gathered until then are still applied.
+    fn run_watch_worker(
+        app: AppHandle,
+        state: Arc<AppState>,
+        events: Receiver<notify::Result<notify::Event>>,
+    ) {
+        let mut disconnected = false;
+        while !disconnected {
+            let Ok(first) = events.recv() else {
+                return;
+            };
+            let quiet_ms = state.config.lock().unwrap().watch_quiet_ms;
+            let quiet = Duration::from_millis(Self::clamp_watch_quiet_ms(quiet_ms) as u64);
+            let deadline = Instant::now() + quiet * WATCH_MAX_BATCH_PERIODS;
+            let mut batch = WatchBatch::default();
+            let mut next = Some(first);
+            while let Some(res) = next.take() {
+                match res {
+                    Ok(event) => batch.push(event),
+                    Err(err) => {
+                        eprintln!("[watcher] {err}");
+                        batch.mark_overflow();
+                    }
+                }
+                let remaining = deadline.saturating_duration_since(Instant::now());
+                if remaining.is_zero() {
+                    break;
+                }
+                match events.recv_timeout(quiet.min(remaining)) {
+                    Ok(res) => next = Some(res),
+                    Err(RecvTimeoutError::Timeout) => {}
+                    Err(RecvTimeoutError::Disconnected) => disconnected = true,
+                }
+            }
+
+            if batch.is_empty() {
+                continue;
+            }
+            if batch.needs_rescan() {
+                // Commands that write the library (tag edits) refresh and emit
+                // themselves, so their own writes come back here unchanged.
+                let previous = state.prompts.read().unwrap().clone();
+                let prompts = Self::refresh_prompts(&state);
+                if prompts != previous {
+                    let _ = app.emit(EVENT_PROMPTS_UPDATED, prompts);
+                }
+            } else if let Some(delta) = Self::reindex_paths(&state, batch.paths()) {
+                let _ = app.emit(EVENT_PROMPTS_CHANGED, delta);
+            }
+        }
+    }
+
     pub fn clamp_preview_chars(value: u32) -> u32 {
         value.clamp(PREVIEW_CHARS_MIN, PREVIEW_CHARS_MAX)
     }
+
+    pub fn clamp_watch_quiet_ms(value: u32) -> u32 {
+        value.clamp(WATCH_QUIET_MS_MIN, WATCH_QUIET_MS_MAX)
+    }
+}
+
+fn root_paths(roots: &[PromptRoot]) -> Vec<PathBuf> {
+    roots.iter().map(|root| PathBuf::from(&root.path)).collect()
+}
+
+/// Publishes the merged aliases, policy and tag info of the library.
+fn set_library_meta(state: &AppState, library: TagsMeta) {
+    *state.tag_aliases.write().unwrap() = library.aliases;
+    *state.tag_policy.write().unwrap() = library.policy;
+    *state.tag_info.write().unwrap() = library.tag_info;
+}
+
+fn save_index_cache(path: &Path, cache: &mut IndexCache) {
+    if !cache.is_dirty() {
+        return;
+    }
+    if let Err(error) = cache.save(path) {
+        eprintln!("[index_cache] save failed: {error}");
+    }
+}
+
+/// Drops empty files created moments ago by `create_prompt_file` until they
+/// get content or their grace period ends.
+fn hide_pending(state: &AppState, prompts: Vec<PromptEntry>) -> Vec<PromptEntry> {
+    let pending = { state.pending_paths.lock().unwrap().clone() };
+    if pending.is_empty() {
+        return prompts;
+    }
+    let mut next_pending = HashMap::new();
+    let mut visible = Vec::new();
+    let now = now_ms();
+
+    for prompt in prompts {
+        if let Some(created_at) = pending.get(&prompt.id) {
+            if still_pending(&prompt, *created_at, now) {
+                next_pending.insert(prompt.id.clone(), *created_at);
+                continue;
+            }
+        }
+        visible.push(prompt);
+    }
+
+    *state.pending_paths.lock().unwrap() = next_pending;
+    visible
+}
+
+/// `hide_pending` for a watcher batch: only the pending entries among `fresh`
+/// are checked, the others keep their grace period.
+fn hide_pending_fresh(state: &AppState, fresh: Vec<PromptEntry>) -> Vec<PromptEntry> {
+    let mut pending = state.pending_paths.lock().unwrap();
+    if pending.is_empty() {
+        return fresh;
+    }
+    let now = now_ms();
+    fresh
+        .into_iter()
+        .filter(|prompt| match pending.get(&prompt.id) {
+            Some(created_at) if still_pending(prompt, *created_at, now) => false,
+            Some(_) => {
+                pending.remove(&prompt.id);
+                true
+            }
+            None => true,
+        })
+        .collect()
+}
+
+fn still_pending(prompt: &PromptEntry, created_at: u128, now: u128) -> bool {
+    let size = fs::metadata(&prompt.path).map(|m| m.len()).unwrap_or(0);
+    size == 0 && now.saturating_sub(created_at) < PENDING_PROMPT_TTL_MS
+}
+
+fn now_ms() -> u128 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_millis())
+        .unwrap_or(0)
+}
+
+/// The prompts at `path`: the file itself, or every prompt file below a
+/// directory. Empty when the path no longer exists.
+fn read_path(indexer: &RootIndexer, path: &Path, preview_chars: usize) -> Vec<PromptEntry> {
+    if path.is_dir() {
+        return WalkDir::new(path)
+            .follow_links(true)
+            .into_iter()
+            .flatten()
+            .filter(|entry| entry.file_type().is_file())
+            .filter_map(|entry| indexer.read(entry.path(), preview_chars))
+            .collect();
+    }
+    indexer.read(path, preview_chars).into_iter().collect()
 }
 
-fn resolve_prompts_root(state: &AppState) -> Result<PathBuf, String> {
-    let dir = state.config.lock().unwrap().prompts_dir.clone();
-    if dir.trim().is_empty() {
+fn search_signals(
+    state: &AppState,
+    favorites_only: bool,
+) -> (Option<HashSet<String>>, HashMap<String, i32>) {
+    let now = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|value| value.as_millis() as i64)
+        .unwrap_or(0);
+    let config = state.config.lock().unwrap();
+    let favorites = if favorites_only {
+        Some(config.favorites.iter().cloned().collect())
+    } else {
+        None
+    };
+    (favorites, frecency_boosts(&config, now))
+}
+
+/// Canonical paths of the enabled prompt roots.
+pub(crate) fn resolve_prompt_roots(state: &AppState) -> Result<Vec<PathBuf>, String> {
+    let roots = state.config.lock().unwrap().active_roots();
+    if roots.is_empty() {
         return Err("提示词目录未配置".to_string());
     }
-    let root = PathBuf::from(dir);
-    root.canonicalize()
-        .map_err(|e| format!("解析提示词目录失败: {e}"))
+    roots
+        .iter()
+        .map(|root| {
+            PathBuf::from(&root.path)
+                .canonicalize()
+                .map_err(|e| format!("解析提示词目录失败: {e}"))
+        })
+        .collect()
 }
 
-fn resolve_prompt_path(root: &Path, path: &Path) -> Result<PathBuf, String> {
+/// The enabled root configured as `root`, for commands that act on one root.
+pub(crate) fn enabled_root(state: &AppState, root: &str) -> Result<PathBuf, String> {
+    state
+        .config
+        .lock()
+        .unwrap()
+        .active_roots()
+        .into_iter()
+        .find(|candidate| candidate.path == root)
+        .map(|candidate| PathBuf::from(candidate.path))
+        .ok_or_else(|| "目标目录不是已启用的提示词目录".to_string())
+}
+
+/// Canonicalizes `path` and returns it with the root that contains it.
+pub(crate) fn resolve_prompt_path<'a>(
+    roots: &'a [PathBuf],
+    path: &Path,
+) -> Result<(&'a PathBuf, PathBuf), String> {
     let target = path
         .canonicalize()
         .map_err(|e| format!("解析路径失败: {e}"))?;
-    if !target.starts_with(root) {
-        return Err("路径不在提示词目录中".to_string());
-    }
-    Ok(target)
+    let root = root_for_path(roots, &target).ok_or_else(|| "路径不在提示词目录中".to_string())?;
+    Ok((root, target))
 }
 
-fn build_prompt_tag_map(state: &AppState) -> HashMap<String, Vec<String>> {
-    let prompts = state.prompts.read().unwrap();
-    let mut map = HashMap::new();
-    for prompt in prompts.iter() {
-        map.insert(prompt.id.clone(), prompt.tags.clone());
-    }
-    map
+/// The innermost root containing `path`, so nested roots resolve to the
+/// closest one.
+pub(crate) fn root_for_path<'a>(roots: &'a [PathBuf], path: &Path) -> Option<&'a PathBuf> {
+    roots
+        .iter()
+        .filter(|root| path.starts_with(root))
+        .max_by_key(|root| root.components().count())
 }
 
-fn normalize_input_tags(raw: Vec<String>) -> Result<Vec<String>, String> {
+pub(crate) fn normalize_input_tags(
+    raw: Vec<String>,
+    policy: &TagPolicy,
+) -> Result<Vec<String>, String> {
     let mut tags = Vec::new();
     let mut seen = HashSet::new();
     for value in raw {
@@ -384,8 +1015,9 @@ fn normalize_input_tags(raw: Vec<String>) -> Result<Vec<String>, String> {
             if token.is_empty() {
                 continue;
             }
-            let normalized = normalize_tag(token)
-                .ok_or_else(|| "标签仅允许中英文数字，长度 1-10".to_string())?;
+            let normalized = policy
+                .normalize(token)
+                .map_err(|reason| reason.describe(token))?;
             if seen.insert(normalized.clone()) {
                 tags.push(normalized);
             }
@@ -394,6 +1026,16 @@ fn normalize_input_tags(raw: Vec<String>) -> Result<Vec<String>, String> {
     Ok(tags)
 }
 
+/// Rewrites normalized input tags through the alias map, dropping the
+/// duplicates an alias and its canonical tag would leave.
+fn canonical_tags(tags: &[String], aliases: &HashMap<String, String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+    tags.iter()
+        .map(|tag| canonical_tag(aliases, tag))
+        .filter(|tag| seen.insert(tag.clone()))
+        .collect()
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -420,10 +1062,10 @@ mod tests {
 
     #[test]
     fn normalize_input_tags_dedupes_and_normalizes() {
-        let tags = normalize_input_tags(vec![
-            "#Tag1 tag1 标签2".to_string(),
-            "foo #foo".to_string(),
-        ])
+        let tags = normalize_input_tags(
+            vec!["#Tag1 tag1 标签2".to_string(), "foo #foo".to_string()],
+            &TagPolicy::default(),
+        )
         .unwrap();
         assert_eq!(
             tags,
@@ -433,7 +1075,224 @@ mod tests {
 
     #[test]
     fn normalize_input_tags_rejects_invalid() {
-        assert!(normalize_input_tags(vec!["tag-1".to_string()]).is_err());
+        let policy = TagPolicy::default();
+        assert_eq!(
+            normalize_input_tags(vec!["ok #tag-1".to_string()], &policy).unwrap_err(),
+            "标签「tag-1」无效：不允许字符「-」"
+        );
+        let policy = TagPolicy {
+            separators: "-".to_string(),
+            ..Default::default()
+        };
+        assert_eq!(
+            normalize_input_tags(vec!["tag-1".to_string()], &policy).unwrap(),
+            vec!["tag-1".to_string()]
+        );
+    }
+
+    #[test]
+    fn folder_tags_are_inherited_but_not_copied() {
+        let dir = make_temp_dir("folder-tags");
+        let folder = dir.join("legal");
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("nda.txt");
+        fs::write(&path, "body").unwrap();
+        let state = make_state(&dir);
+
+        let prompts = PromptsService::set_folder_tags(
+            &state,
+            folder.to_string_lossy().to_string(),
+            vec!["合同".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            prompts[0].tags,
+            vec!["legal".to_string(), "合同".to_string()]
+        );
+
+        let id = path.to_string_lossy().to_string();
+        let prompts = PromptsService::update_prompt_tags(
+            &state,
+            vec![id],
+            vec!["nda".to_string()],
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            prompts[0].tags,
+            vec!["legal".to_string(), "nda".to_string(), "合同".to_string()]
+        );
+        let meta = load_tags_meta(&dir).unwrap();
+        assert_eq!(
+            meta.tags_by_path["legal/nda.txt"],
+            vec!["legal".to_string(), "nda".to_string()]
+        );
+        assert_eq!(meta.tags_by_path["legal/"], vec!["合同".to_string()]);
+    }
+
+    #[test]
+    fn update_prompt_tags_removes_applied_tags_and_resolves_aliases() {
+        let dir = make_temp_dir("applied-tags");
+        let folder = dir.join("legal");
+        fs::create_dir_all(&folder).unwrap();
+        let path = folder.join("nda.txt");
+        fs::write(&path, "uses SQL").unwrap();
+        fs::write(
+            dir.join(".tag-rules.json"),
+            r#"{"rules":[{"tags":["db"],"body_regex":"sql"}]}"#,
+        )
+        .unwrap();
+        let mut meta = TagsMeta::default();
+        meta.aliases.insert("邮件".to_string(), "email".to_string());
+        meta.tags_by_path
+            .insert("legal/".to_string(), vec!["合同".to_string()]);
+        save_tags_meta(&dir, &meta).unwrap();
+        let state = make_state(&dir);
+        let id = path.to_string_lossy().to_string();
+
+        let prompts = PromptsService::update_prompt_tags(
+            &state,
+            vec![id.clone()],
+            vec!["邮件".to_string()],
+            vec!["合同".to_string(), "db".to_string()],
+        )
+        .unwrap();
+        assert_eq!(
+            prompts[0].tags,
+            vec!["email".to_string(), "legal".to_string()]
+        );
+        let meta = load_tags_meta(&dir).unwrap();
+        assert_eq!(
+            meta.tags_by_path["legal/nda.txt"],
+            vec!["email".to_string(), "legal".to_string()]
+        );
+        assert_eq!(
+            meta.removed_by_path["legal/nda.txt"],
+            vec!["db".to_string(), "合同".to_string()]
+        );
+
+        let prompts = PromptsService::update_prompt_tags(
+            &state,
+            vec![id],
+            vec!["合同".to_string()],
+            Vec::new(),
+        )
+        .unwrap();
+        assert_eq!(
+            prompts[0].tags,
+            vec!["email".to_string(), "legal".to_string(), "合同".to_string()]
+        );
+        let meta = load_tags_meta(&dir).unwrap();
+        assert_eq!(
+            meta.removed_by_path["legal/nda.txt"],
+            vec!["db".to_string()]
+        );
+    }
+
+    #[test]
+    fn index_cache_is_kept_current_and_served_at_startup() {
+        let dir = make_temp_dir("prompts-cache");
+        let library = dir.join("lib");
+        fs::create_dir_all(&library).unwrap();
+        let cache_path = dir.join("index-cache.json");
+        let start = || {
+            let config = AppConfig {
+                prompts_dir: library.to_string_lossy().to_string(),
+                ..AppConfig::default()
+            };
+            Arc::new(AppState::new(config).with_index_cache(cache_path.clone()))
+        };
+        let bodies = |state: &AppState| {
+            let prompts = state.prompts.read().unwrap();
+            prompts
+                .iter()
+                .map(|prompt| prompt.body.clone())
+                .collect::<Vec<_>>()
+        };
+        fs::write(library.join("a.txt"), "first").unwrap();
+        let state = start();
+        PromptsService::refresh_prompts(&state);
+        let added = library.join("b.txt");
+        fs::write(&added, "second").unwrap();
+        PromptsService::reindex_paths(&state, &[added]).unwrap();
+
+        // The next start lists the saved entries before reading any file.
+        fs::remove_file(library.join("a.txt")).unwrap();
+        let state = start();
+        PromptsService::load_cached_prompts(&state);
+        assert_eq!(bodies(&state), vec!["first", "second"]);
+        PromptsService::refresh_prompts(&state);
+        assert_eq!(bodies(&state), vec!["second"]);
+        let state = start();
+        PromptsService::load_cached_prompts(&state);
+        assert_eq!(bodies(&state), vec!["second"]);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn reindex_paths_resolves_events_through_symlinked_roots() {
+        let dir = make_temp_dir("reindex-symlink");
+        let real = dir.join("real");
+        fs::create_dir_all(&real).unwrap();
+        let link = dir.join("link");
+        std::os::unix::fs::symlink(&real, &link).unwrap();
+        let state = make_state(&link);
+        PromptsService::refresh_prompts(&state);
+
+        // Some backends report the target path instead of the watched one.
+        fs::write(real.join("a.txt"), "a").unwrap();
+        let delta = PromptsService::reindex_paths(&state, &[real.join("a.txt")]).unwrap();
+        let id = link.join("a.txt").to_string_lossy().to_string();
+        assert_eq!(delta.upserted[0].id, id);
+
+        fs::remove_file(real.join("a.txt")).unwrap();
+        let delta = PromptsService::reindex_paths(&state, &[real.join("a.txt")]).unwrap();
+        assert_eq!(delta.removed, vec![id]);
+        assert!(state.prompts.read().unwrap().is_empty());
+    }
+
+    #[test]
+    fn reindex_paths_updates_only_touched_entries() {
+        let dir = make_temp_dir("reindex-paths");
+        let keep = dir.join("keep.txt");
+        let edit = dir.join("edit.txt");
+        let drafts = dir.join("drafts");
+        fs::create_dir_all(&drafts).unwrap();
+        fs::write(&keep, "keep").unwrap();
+        fs::write(&edit, "old").unwrap();
+        fs::write(drafts.join("a.txt"), "a").unwrap();
+        let state = make_state(&dir);
+        PromptsService::refresh_prompts(&state);
+
+        fs::write(&edit, "new").unwrap();
+        let added = dir.join("added.md");
+        fs::write(&added, "added").unwrap();
+        fs::remove_dir_all(&drafts).unwrap();
+        let delta =
+            PromptsService::reindex_paths(&state, &[edit.clone(), added.clone(), drafts.clone()])
+                .unwrap();
+        let upserted: Vec<&str> = delta.upserted.iter().map(|p| p.title.as_str()).collect();
+        assert_eq!(upserted, vec!["edit", "added"]);
+        assert_eq!(
+            delta.removed,
+            vec![drafts.join("a.txt").to_string_lossy().to_string()]
+        );
+        let prompts = state.prompts.read().unwrap().clone();
+        let mut bodies: Vec<(&str, &str)> = prompts
+            .iter()
+            .map(|prompt| (prompt.title.as_str(), prompt.body.as_str()))
+            .collect();
+        bodies.sort();
+        assert_eq!(
+            bodies,
+            vec![("added", "added"), ("edit", "new"), ("keep", "keep")]
+        );
+        let results = PromptsService::search(&state, "new", 10, false, false).unwrap();
+        assert_eq!(results.len(), 1);
+
+        // Unchanged files and other file types are not reported again.
+        assert!(PromptsService::reindex_paths(&state, &[keep]).is_none());
+        assert!(PromptsService::reindex_paths(&state, &[dir.join("notes.log")]).is_none());
     }
 
     #[test]
@@ -452,7 +1311,7 @@ mod tests {
             .unwrap()
             .insert(path.to_string_lossy().to_string(), now);
 
-        let results = PromptsService::refresh_prompts(&state, &dir);
+        let results = PromptsService::refresh_prompts(&state);
         assert!(results.is_empty());
     }
 
@@ -473,8 +1332,106 @@ mod tests {
             .unwrap()
             .insert(path.to_string_lossy().to_string(), old);
 
-        let results = PromptsService::refresh_prompts(&state, &dir);
+        let results = PromptsService::refresh_prompts(&state);
         assert_eq!(results.len(), 1);
         assert_eq!(results[0].path, path.to_string_lossy().to_string());
     }
+
+    #[test]
+    fn search_uses_index_built_on_refresh() {
+        let dir = make_temp_dir("search-index");
+        fs::write(dir.join("Email reply.txt"), "reply politely").unwrap();
+        fs::write(dir.join("Summary.txt"), "write an email digest").unwrap();
+        let state = make_state(&dir);
+        PromptsService::refresh_prompts(&state);
+
+        let results = PromptsService::search(&state, "email", 10, false, false).unwrap();
+        let titles: Vec<&str> = results.iter().map(|prompt| prompt.title.as_str()).collect();
+        assert_eq!(titles, vec!["Email reply", "Summary"]);
+    }
+
+    #[test]
+    fn refresh_prompts_merges_enabled_roots() {
+        let main = make_temp_dir("roots-main");
+        let team = make_temp_dir("roots-team");
+        let off = make_temp_dir("roots-off");
+        fs::write(main.join("Mine.txt"), "mine").unwrap();
+        fs::write(team.join("Shared.txt"), "shared").unwrap();
+        fs::write(off.join("Hidden.txt"), "hidden").unwrap();
+        let state = make_state(&main);
+        state.config.lock().unwrap().prompt_roots = vec![
+            PromptRoot {
+                path: team.to_string_lossy().to_string(),
+                label: "团队".to_string(),
+                enabled: true,
+            },
+            PromptRoot {
+                path: off.to_string_lossy().to_string(),
+                label: String::new(),
+                enabled: false,
+            },
+        ];
+
+        let mut results = PromptsService::refresh_prompts(&state);
+        results.sort_by(|a, b| a.title.cmp(&b.title));
+        let sources: Vec<(&str, &str)> = results
+            .iter()
+            .map(|prompt| (prompt.title.as_str(), prompt.source.as_str()))
+            .collect();
+        let main_label = main.file_name().unwrap().to_string_lossy().to_string();
+        assert_eq!(
+            sources,
+            vec![("Mine", main_label.as_str()), ("Shared", "团队")]
+        );
+
+        let roots = vec![main.canonicalize().unwrap(), team.canonicalize().unwrap()];
+        let (root, _) = resolve_prompt_path(&roots, &team.join("Shared.txt")).unwrap();
+        assert_eq!(root, &roots[1]);
+        assert!(resolve_prompt_path(&roots, &off.join("Hidden.txt")).is_err());
+
+        let created = PromptsService::create_prompt_file(
+            &state,
+            "New".to_string(),
+            Some(team.to_string_lossy().to_string()),
+        )
+        .unwrap();
+        assert!(Path::new(&created).starts_with(&team));
+        let primary = PromptsService::create_prompt_file(&state, "New".to_string(), None).unwrap();
+        assert!(Path::new(&primary).starts_with(&main));
+        assert!(PromptsService::create_prompt_file(
+            &state,
+            "New".to_string(),
+            Some(off.to_string_lossy().to_string()),
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn every_root_is_indexed_with_the_library_policy() {
+        let main = make_temp_dir("policy-main");
+        let team = make_temp_dir("policy-team");
+        let policy = TagPolicy {
+            separators: "-".to_string(),
+            ..TagPolicy::default()
+        };
+        save_tags_meta(
+            &main,
+            &TagsMeta {
+                policy: policy.clone(),
+                ..TagsMeta::default()
+            },
+        )
+        .unwrap();
+        fs::write(team.join("Shared #gpt-4.txt"), "shared").unwrap();
+        let state = make_state(&main);
+        state.config.lock().unwrap().prompt_roots = vec![PromptRoot {
+            path: team.to_string_lossy().to_string(),
+            label: String::new(),
+            enabled: true,
+        }];
+
+        let results = PromptsService::refresh_prompts(&state);
+        assert_eq!(results[0].tags, vec!["gpt-4".to_string()]);
+        assert_eq!(*state.tag_policy.read().unwrap(), policy);
+    }
 }
@@ -0,0 +1,916 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::Arc;
+use tauri::{AppHandle, Emitter};
+
+use crate::config::{save, AppConfig};
+use crate::constants::EVENT_PROMPTS_UPDATED;
+use crate::prompts::{fallback_tags, PromptEntry};
+use crate::services::prompts_service::{
+    enabled_root, resolve_prompt_path, resolve_prompt_roots, root_for_path, PromptsService,
+};
+use crate::state::AppState;
+use crate::tag_edit::TagEdit;
+use crate::tag_policy::TagPolicy;
+use crate::tag_rules::{
+    load_tag_rules, save_tag_rules, tag_rules_path, validate_rules, TagRule, TagRules, TagRulesFile,
+};
+use crate::tags_meta::{
+    canonical_tag, load_library_meta, load_tags_meta, merge_aliases, path_to_key, save_tags_meta,
+    set_alias, tags_meta_path, touch_updated_at, validate_tag_info, TagInfo, TagsMeta,
+};
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct FileRename {
+    pub from: String,
+    pub to: String,
+}
+
+/// What a tag rename/merge/delete changes (or would change on a dry run).
+#[derive(Debug, Clone, Serialize)]
+pub struct TagEditReport {
+    pub dry_run: bool,
+    /// Paths of the prompts whose tags change.
+    pub prompts: Vec<String>,
+    pub renames: Vec<FileRename>,
+}
+
+/// A prompt an unsaved tag rule would match.
+#[derive(Debug, Clone, Serialize)]
+pub struct TagRuleMatch {
+    pub path: String,
+    pub title: String,
+    /// Rule tags the prompt does not have yet.
+    pub added: Vec<String>,
+}
+
+struct TagEditPlan {
+    prompts: Vec<String>,
+    renames: Vec<FileRename>,
+    /// Rewritten `.tags.json` per root, only for roots that change.
+    metas: Vec<(PathBuf, TagsMeta)>,
+    /// Rewritten `.tag-rules.json` per root, only for roots that change.
+    rules: Vec<(PathBuf, TagRulesFile)>,
+}
+
+pub struct TagsService;
+
+impl TagsService {
+    pub fn list_aliases(state: &AppState) -> HashMap<String, String> {
+        state.tag_aliases.read().unwrap().clone()
+    }
+
+    /// Records `alias -> canonical` in the library owner's `.tags.json`, which
+    /// takes precedence over aliases read from the other roots.
+    pub fn set_alias(
+        state: &Arc<AppState>,
+        alias: String,
+        canonical: String,
+    ) -> Result<Vec<PromptEntry>, String> {
+        let alias = normalize_tag_input(state, &alias)?;
+        let canonical = normalize_tag_input(state, &canonical)?;
+        let root = owner_root(state)?;
+        let mut meta = load_tags_meta(&root)?;
+        set_alias(&mut meta, &alias, &canonical)?;
+        touch_updated_at(&mut meta);
+        save_tags_meta(&root, &meta)?;
+        Ok(PromptsService::refresh_prompts(state))
+    }
+
+    /// Removes `alias` from the library owner. An alias that only another
+    /// root defines has to be removed in that root's file.
+    pub fn remove_alias(state: &Arc<AppState>, alias: String) -> Result<Vec<PromptEntry>, String> {
+        let alias = normalize_tag_input(state, &alias)?;
+        let root = owner_root(state)?;
+        let mut meta = load_tags_meta(&root)?;
+        if meta.aliases.remove(&alias).is_some() {
+            touch_updated_at(&mut meta);
+            save_tags_meta(&root, &meta)?;
+        } else if state.tag_aliases.read().unwrap().contains_key(&alias) {
+            return Err(defined_elsewhere("别名"));
+        }
+        Ok(PromptsService::refresh_prompts(state))
+    }
+
+    /// Rewrites stored tags to their canonical form through the library's
+    /// aliases, so aliases can be removed later without splitting the library
+    /// again. Stored tags belong to their root, so each root is rewritten; a
+    /// root that fails is reported without stopping the others.
+    pub fn merge_aliases(state: &Arc<AppState>) -> Result<Vec<PromptEntry>, String> {
+        let aliases = state.tag_aliases.read().unwrap().clone();
+        let mut errors = Vec::new();
+        for root in resolve_prompt_roots(state)? {
+            let merged = load_tags_meta(&root).and_then(|mut meta| {
+                if merge_aliases(&mut meta, &aliases) == 0 {
+                    return Ok(());
+                }
+                touch_updated_at(&mut meta);
+                save_tags_meta(&root, &meta)
+            });
+            if let Err(error) = merged {
+                errors.push(format!("{}: {error}", root.display()));
+            }
+        }
+        let prompts = PromptsService::refresh_prompts(state);
+        if errors.is_empty() {
+            Ok(prompts)
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
+    pub fn get_policy(state: &AppState) -> TagPolicy {
+        state.tag_policy.read().unwrap().clone()
+    }
+
+    /// Saves the tag policy to the library owner, whose policy every root is
+    /// indexed with, and re-indexes so tags that were dropped under the old
+    /// rules show up again.
+    pub fn set_policy(
+        state: &Arc<AppState>,
+        policy: TagPolicy,
+    ) -> Result<Vec<PromptEntry>, String> {
+        policy.validate()?;
+        let root = owner_root(state)?;
+        let mut meta = load_tags_meta(&root)?;
+        if meta.policy != policy {
+            meta.policy = policy;
+            touch_updated_at(&mut meta);
+            save_tags_meta(&root, &meta)?;
+        }
+        Ok(PromptsService::refresh_prompts(state))
+    }
+
+    pub fn list_tag_info(state: &AppState) -> HashMap<String, TagInfo> {
+        state.tag_info.read().unwrap().clone()
+    }
+
+    /// Stores display metadata for `tag` in the library owner. Tag info does
+    /// not affect the index, so only the cached table is reloaded.
+    pub fn set_tag_info(
+        state: &AppState,
+        tag: String,
+        info: TagInfo,
+    ) -> Result<HashMap<String, TagInfo>, String> {
+        let tag = normalize_tag_input(state, &tag)?;
+        validate_tag_info(&info)?;
+        let roots = resolve_prompt_roots(state)?;
+        let root = &roots[0];
+        let mut meta = load_tags_meta(root)?;
+        meta.tag_info.insert(tag, info);
+        touch_updated_at(&mut meta);
+        save_tags_meta(root, &meta)?;
+        Ok(reload_tag_info(state, &roots))
+    }
+
+    pub fn delete_tag_info(
+        state: &AppState,
+        tag: String,
+    ) -> Result<HashMap<String, TagInfo>, String> {
+        let tag = normalize_tag_input(state, &tag)?;
+        let roots = resolve_prompt_roots(state)?;
+        let root = &roots[0];
+        let mut meta = load_tags_meta(root)?;
+        if meta.tag_info.remove(&tag).is_some() {
+            touch_updated_at(&mut meta);
+            save_tags_meta(root, &meta)?;
+        } else if state.tag_info.read().unwrap().contains_key(&tag) {
+            return Err(defined_elsewhere("标签信息"));
+        }
+        Ok(reload_tag_info(state, &roots))
+    }
+
+    /// Rules belong to the root they are stored in and only tag prompts
+    /// below it, so they are read and saved per root.
+    pub fn get_tag_rules(state: &AppState, root: String) -> Result<Vec<TagRule>, String> {
+        Ok(load_tag_rules(&enabled_root(state, &root)?)?.rules)
+    }
+
+    pub fn set_tag_rules(
+        state: &Arc<AppState>,
+        root: String,
+        mut rules: Vec<TagRule>,
+    ) -> Result<Vec<PromptEntry>, String> {
+        let root = enabled_root(state, &root)?;
+        let policy = state.tag_policy.read().unwrap().clone();
+        validate_rules(&mut rules, &policy)?;
+        let file = TagRulesFile {
+            rules,
+            ..Default::default()
+        };
+        save_tag_rules(&root, &file)?;
+        Ok(PromptsService::refresh_prompts(state))
+    }
+
+    /// Lists the indexed prompts `rule` would tag, without saving it.
+    pub fn preview_tag_rule(
+        state: &AppState,
+        mut rule: TagRule,
+    ) -> Result<Vec<TagRuleMatch>, String> {
+        let policy = state.tag_policy.read().unwrap().clone();
+        validate_rules(std::slice::from_mut(&mut rule), &policy)?;
+        let compiled = TagRules::compile(std::slice::from_ref(&rule))?;
+        let roots: Vec<PathBuf> = {
+            let config = state.config.lock().unwrap();
+            config
+                .active_roots()
+                .into_iter()
+                .map(|root| PathBuf::from(root.path))
+                .collect()
+        };
+        let aliases = state.tag_aliases.read().unwrap().clone();
+        let prompts = state.prompts.read().unwrap();
+        let mut matches = Vec::new();
+        for prompt in prompts.iter() {
+            let path = Path::new(&prompt.path);
+            let Some(root) = root_for_path(&roots, path) else {
+                continue;
+            };
+            let raw = compiled.tags_for(root, path, &prompt.body);
+            if raw.is_empty() {
+                continue;
+            }
+            let mut added = Vec::new();
+            for tag in raw {
+                let Ok(tag) = policy.normalize(&tag) else {
+                    continue;
+                };
+                let tag = canonical_tag(&aliases, &tag);
+                if !prompt.tags.contains(&tag) && !added.contains(&tag) {
+                    added.push(tag);
+                }
+            }
+            matches.push(TagRuleMatch {
+                path: prompt.path.clone(),
+                title: prompt.title.clone(),
+                added,
+            });
+        }
+        Ok(matches)
+    }
+
+    pub fn rename_tag(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        from: String,
+        to: String,
+        rename_files: bool,
+        dry_run: bool,
+    ) -> Result<TagEditReport, String> {
+        Self::merge_tags(app, state, vec![from], to, rename_files, dry_run)
+    }
+
+    pub fn merge_tags(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        sources: Vec<String>,
+        target: String,
+        rename_files: bool,
+        dry_run: bool,
+    ) -> Result<TagEditReport, String> {
+        let edit = build_edit(state, &sources, Some(&target))?;
+        Self::run_edit(app, state, &edit, rename_files, dry_run)
+    }
+
+    pub fn delete_tag(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        tag: String,
+        rename_files: bool,
+        dry_run: bool,
+    ) -> Result<TagEditReport, String> {
+        let edit = build_edit(state, &[tag], None)?;
+        Self::run_edit(app, state, &edit, rename_files, dry_run)
+    }
+
+    /// Plans the edit, then (unless `dry_run`) renames files, saves every
+    /// `.tags.json` and moves per-prompt config over to the new paths before a
+    /// single refresh and emit.
+    fn run_edit(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        edit: &TagEdit,
+        rename_files: bool,
+        dry_run: bool,
+    ) -> Result<TagEditReport, String> {
+        let plan = plan_edit(state, edit, rename_files)?;
+        let report = TagEditReport {
+            dry_run,
+            prompts: plan.prompts.clone(),
+            renames: plan.renames.clone(),
+        };
+        if dry_run {
+            return Ok(report);
+        }
+
+        apply_plan(plan)?;
+        if !report.renames.is_empty() {
+            let mut config = state.config.lock().unwrap();
+            for rename in &report.renames {
+                rename_prompt_id(&mut config, &rename.from, &rename.to);
+            }
+            save(app, &config)?;
+        }
+        let prompts = PromptsService::refresh_prompts(state);
+        let _ = app.emit(EVENT_PROMPTS_UPDATED, prompts);
+        Ok(report)
+    }
+}
+
+/// The first enabled root, which owns the library-level metadata.
+fn owner_root(state: &AppState) -> Result<PathBuf, String> {
+    Ok(resolve_prompt_roots(state)?.swap_remove(0))
+}
+
+fn defined_elsewhere(what: &str) -> String {
+    format!("该{what}定义在其他提示词目录的 .tags.json 中，请在该目录中修改")
+}
+
+fn reload_tag_info(state: &AppState, roots: &[PathBuf]) -> HashMap<String, TagInfo> {
+    let tag_info = load_library_meta(roots).tag_info;
+    *state.tag_info.write().unwrap() = tag_info.clone();
+    tag_info
+}
+
+fn build_edit(
+    state: &AppState,
+    sources: &[String],
+    target: Option<&str>,
+) -> Result<TagEdit, String> {
+    let aliases = state.tag_aliases.read().unwrap().clone();
+    let mut normalized = Vec::new();
+    for source in sources {
+        let tag = canonical_tag(&aliases, &normalize_tag_input(state, source)?);
+        if !normalized.contains(&tag) {
+            normalized.push(tag);
+        }
+    }
+    if normalized.is_empty() {
+        return Err("标签不能为空".to_string());
+    }
+    let target = match target {
+        Some(raw) => Some(canonical_tag(&aliases, &normalize_tag_input(state, raw)?)),
+        None => None,
+    };
+    if let Some(target) = &target {
+        normalized.retain(|source| source != target);
+        if normalized.is_empty() {
+            return Err("源标签与目标标签相同".to_string());
+        }
+    }
+    Ok(TagEdit {
+        sources: normalized,
+        target,
+    })
+}
+
+fn plan_edit(state: &AppState, edit: &TagEdit, rename_files: bool) -> Result<TagEditPlan, String> {
+    let roots = resolve_prompt_roots(state)?;
+    let prompts = state.prompts.read().unwrap().clone();
+    let policy = state.tag_policy.read().unwrap().clone();
+    let mut metas: HashMap<PathBuf, (TagsMeta, bool)> = HashMap::new();
+    for root in &roots {
+        let mut meta = load_tags_meta(root)?;
+        let mut changed = false;
+        let entries = meta
+            .tags_by_path
+            .values_mut()
+            .chain(meta.removed_by_path.values_mut());
+        for tags in entries {
+            if tags.iter().any(|tag| edit.affects(tag)) {
+                *tags = edit.apply(tags);
+                changed = true;
+            }
+        }
+        meta.removed_by_path.retain(|_, tags| !tags.is_empty());
+        let moved: Vec<String> = meta
+            .tag_info
+            .keys()
+            .filter(|tag| edit.affects(tag))
+            .cloned()
+            .collect();
+        for tag in moved {
+            let info = meta.tag_info.remove(&tag).unwrap_or_default();
+            if let Some(next) = edit.apply(&[tag]).pop() {
+                meta.tag_info.entry(next).or_insert(info);
+            }
+            changed = true;
+        }
+        meta.aliases.retain(|_, canonical| {
+            if !edit.affects(canonical) {
+                return true;
+            }
+            changed = true;
+            match edit.apply(std::slice::from_ref(canonical)).pop() {
+                Some(next) => {
+                    *canonical = next;
+                    true
+                }
+                None => false,
+            }
+        });
+        metas.insert(root.clone(), (meta, changed));
+    }
+
+    let mut plan = TagEditPlan {
+        prompts: Vec::new(),
+        renames: Vec::new(),
+        metas: Vec::new(),
+        rules: Vec::new(),
+    };
+    for root in &roots {
+        let mut file = load_tag_rules(root)?;
+        if !file
+            .rules
+            .iter()
+            .any(|rule| rule.tags.iter().any(|tag| edit.affects(tag)))
+        {
+            continue;
+        }
+        for rule in &mut file.rules {
+            rule.tags = edit.apply(&rule.tags);
+        }
+        // A rule left without tags would fail validation on the next save.
+        file.rules.retain(|rule| !rule.tags.is_empty());
+        plan.rules.push((root.clone(), file));
+    }
+    let mut planned_targets = HashSet::new();
+    for prompt in &prompts {
+        if !prompt.tags.iter().any(|tag| edit.affects(tag)) {
+            continue;
+        }
+        let (root, target) = resolve_prompt_path(&roots, Path::new(&prompt.path))?;
+        let Some((meta, changed)) = metas.get_mut(root) else {
+            continue;
+        };
+        plan.prompts.push(prompt.path.clone());
+        let old_key = path_to_key(root, &target);
+        // Only the prompt's own tags are stored per file; folder tags it
+        // inherits were already rewritten in place above.
+        let override_tags = meta.tags_by_path.get(&old_key).cloned();
+        let has_override = override_tags.is_some();
+        let next_tags = override_tags
+            .unwrap_or_else(|| edit.apply(&fallback_tags(&target, root, meta, &policy)));
+
+        let mut new_target = target.clone();
+        if rename_files {
+            let stem = target.file_stem().unwrap_or_default().to_string_lossy();
+            let next_stem = edit.rename_stem(&stem, &policy);
+            if next_stem != stem && !next_stem.is_empty() {
+                let mut file_name = next_stem;
+                if let Some(ext) = target.extension() {
+                    file_name.push('.');
+                    file_name.push_str(&ext.to_string_lossy());
+                }
+                new_target = target.with_file_name(&file_name);
+                if new_target.exists() || !planned_targets.insert(new_target.clone()) {
+                    return Err(format!("目标文件已存在：{}", new_target.display()));
+                }
+                let to = Path::new(&prompt.path).with_file_name(&file_name);
+                plan.renames.push(FileRename {
+                    from: prompt.path.clone(),
+                    to: to.to_string_lossy().to_string(),
+                });
+            }
+        }
+
+        let new_key = path_to_key(root, &new_target);
+        if has_override || fallback_tags(&new_target, root, meta, &policy) != next_tags {
+            meta.tags_by_path.remove(&old_key);
+            meta.tags_by_path.insert(new_key.clone(), next_tags);
+            *changed = true;
+        } else if new_key != old_key {
+            *changed |= meta.tags_by_path.remove(&old_key).is_some();
+        }
+        if new_key != old_key {
+            if let Some(removed) = meta.removed_by_path.remove(&old_key) {
+                meta.removed_by_path.insert(new_key, removed);
+                *changed = true;
+            }
+        }
+    }
+
+    plan.metas = metas
+        .into_iter()
+        .filter(|(_, (_, changed))| *changed)
+        .map(|(root, (meta, _))| (root, meta))
+        .collect();
+    Ok(plan)
+}
+
+/// Renames the files, then saves the metadata. On any failure the files
+/// already written or renamed are restored, so the library is left as it was.
+fn apply_plan(plan: TagEditPlan) -> Result<(), String> {
+    let mut renamed: Vec<&FileRename> = Vec::new();
+    for rename in &plan.renames {
+        if let Err(err) = fs::rename(&rename.from, &rename.to) {
+            undo_renames(&renamed);
+            return Err(format!("重命名失败: {err}"));
+        }
+        renamed.push(rename);
+    }
+
+    let mut backups: Vec<(PathBuf, Option<Vec<u8>>)> = Vec::new();
+    let saved = || -> Result<(), String> {
+        for (root, mut meta) in plan.metas {
+            backups.push(backup(tags_meta_path(&root)));
+            touch_updated_at(&mut meta);
+            save_tags_meta(&root, &meta)?;
+        }
+        for (root, file) in plan.rules {
+            backups.push(backup(tag_rules_path(&root)));
+            save_tag_rules(&root, &file)?;
+        }
+        Ok(())
+    };
+    if let Err(err) = saved() {
+        for (path, data) in backups.iter().rev() {
+            let restored = match data {
+                Some(data) => fs::write(path, data),
+                None => fs::remove_file(path),
+            };
+            if let Err(restore_err) = restored {
+                eprintln!("[tags] restore {} failed: {restore_err}", path.display());
+            }
+        }
+        undo_renames(&renamed);
+        return Err(err);
+    }
+    Ok(())
+}
+
+fn backup(path: PathBuf) -> (PathBuf, Option<Vec<u8>>) {
+    let data = fs::read(&path).ok();
+    (path, data)
+}
+
+fn undo_renames(renamed: &[&FileRename]) {
+    for rename in renamed.iter().rev() {
+        if let Err(err) = fs::rename(&rename.to, &rename.from) {
+            eprintln!("[tags] undo rename {} failed: {err}", rename.to);
+        }
+    }
+}
+
+/// Moves favorites, recents and use counts from one prompt id to another.
+fn rename_prompt_id(config: &mut AppConfig, from: &str, to: &str) {
+    for id in config
+        .favorites
+        .iter_mut()
+        .chain(config.recent_ids.iter_mut())
+    {
+        if id == from {
+            *id = to.to_string();
+        }
+    }
+    if let Some(value) = config.recent_meta.remove(from) {
+        config.recent_meta.insert(to.to_string(), value);
+    }
+    if let Some(value) = config.use_counts.remove(from) {
+        config.use_counts.insert(to.to_string(), value);
+    }
+}
+
+fn normalize_tag_input(state: &AppState, raw: &str) -> Result<String, String> {
+    let token = raw.trim().trim_start_matches('#');
+    state
+        .tag_policy
+        .read()
+        .unwrap()
+        .normalize(token)
+        .map_err(|reason| reason.describe(token))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::AppConfig;
+    use std::fs;
+    use std::path::Path;
+    use std::time::{SystemTime, UNIX_EPOCH};
+
+    fn make_temp_dir(prefix: &str) -> std::path::PathBuf {
+        let mut dir = std::env::temp_dir();
+        let nanos = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap()
+            .as_nanos();
+        dir.push(format!("{prefix}-{nanos}"));
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    fn make_state(dir: &Path) -> Arc<AppState> {
+        let config = AppConfig {
+            prompts_dir: dir.to_string_lossy().to_string(),
+            ..Default::default()
+        };
+        Arc::new(AppState::new(config))
+    }
+
+    #[test]
+    fn aliases_canonicalize_index_and_queries() {
+        let dir = make_temp_dir("tag-aliases");
+        fs::write(dir.join("回复 #邮件.txt"), "reply").unwrap();
+        fs::write(dir.join("Digest #email.txt"), "digest").unwrap();
+        let state = make_state(&dir);
+
+        let prompts =
+            TagsService::set_alias(&state, "#邮件".to_string(), "email".to_string()).unwrap();
+        assert!(prompts.iter().all(|prompt| prompt.tags == vec!["email"]));
+        assert_eq!(TagsService::list_aliases(&state)["邮件"], "email");
+
+        let results = PromptsService::search(&state, "#邮件", 10, false, false).unwrap();
+        assert_eq!(results.len(), 2);
+
+        TagsService::remove_alias(&state, "邮件".to_string()).unwrap();
+        let results = PromptsService::search(&state, "#邮件", 10, false, false).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn rename_tag_updates_meta_and_file_stems() {
+        let dir = make_temp_dir("tag-rename");
+        fs::create_dir_all(dir.join("Mail")).unwrap();
+        fs::write(dir.join("回复 #mail.txt"), "reply").unwrap();
+        fs::write(dir.join("Mail").join("跟进.txt"), "follow up").unwrap();
+        fs::write(dir.join("Other.txt"), "other").unwrap();
+        let mut meta = TagsMeta::default();
+        meta.tags_by_path.insert(
+            "Other.txt".to_string(),
+            vec!["mail/old".to_string(), "misc".to_string()],
+        );
+        save_tags_meta(&dir, &meta).unwrap();
+        let state = make_state(&dir);
+        PromptsService::refresh_prompts(&state);
+
+        let edit = build_edit(&state, &["#mail".to_string()], Some("email")).unwrap();
+        let plan = plan_edit(&state, &edit, true).unwrap();
+        assert_eq!(plan.prompts.len(), 3);
+        assert_eq!(plan.renames.len(), 1);
+        assert!(plan.renames[0].to.ends_with("回复 #email.txt"));
+        assert!(dir.join("回复 #mail.txt").exists());
+
+        apply_plan(plan).unwrap();
+        let mut prompts = PromptsService::refresh_prompts(&state);
+        prompts.sort_by(|a, b| a.title.cmp(&b.title));
+        let tags: Vec<(&str, Vec<String>)> = prompts
+            .iter()
+            .map(|prompt| (prompt.title.as_str(), prompt.tags.clone()))
+            .collect();
+        assert_eq!(
+            tags,
+            vec![
+                ("Other", vec!["email/old".to_string(), "misc".to_string()]),
+                ("回复 #email", vec!["email".to_string()]),
+                ("跟进", vec!["email".to_string()]),
+            ]
+        );
+        let meta = load_tags_meta(&dir).unwrap();
+        assert!(!meta.tags_by_path.contains_key("回复 #email.txt"));
+        assert_eq!(
+            meta.tags_by_path["Mail/跟进.txt"],
+            vec!["email".to_string()]
+        );
+
+        assert!(build_edit(&state, &["email".to_string()], Some("#Email")).is_err());
+    }
+
+    #[test]
+    fn tag_rules_preview_save_and_follow_renames() {
+        let dir = make_temp_dir("tag-rules");
+        fs::write(dir.join("Report #db.txt"), "SQL report").unwrap();
+        fs::write(dir.join("Query.txt"), "sql query").unwrap();
+        fs::write(dir.join("Notes.txt"), "notes").unwrap();
+        let state = make_state(&dir);
+        PromptsService::refresh_prompts(&state);
+
+        let rule = TagRule {
+            name: "sql".to_string(),
+            tags: vec!["DB".to_string()],
+            body_regex: Some("sql".to_string()),
+            ..Default::default()
+        };
+        let mut matches = TagsService::preview_tag_rule(&state, rule.clone()).unwrap();
+        matches.sort_by(|a, b| a.title.cmp(&b.title));
+        let summary: Vec<(&str, Vec<String>)> = matches
+            .iter()
+            .map(|item| (item.title.as_str(), item.added.clone()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                ("Query", vec!["db".to_string()]),
+                ("Report #db", Vec::new())
+            ]
+        );
+        let root = dir.to_string_lossy().to_string();
+        assert!(TagsService::get_tag_rules(&state, root.clone())
+            .unwrap()
+            .is_empty());
+
+        let prompts = TagsService::set_tag_rules(&state, root.clone(), vec![rule]).unwrap();
+        let query = prompts
+            .iter()
+            .find(|prompt| prompt.title == "Query")
+            .unwrap();
+        assert_eq!(query.tags, vec!["db"]);
+
+        let edit = build_edit(&state, &["db".to_string()], Some("sql")).unwrap();
+        apply_plan(plan_edit(&state, &edit, false).unwrap()).unwrap();
+        assert_eq!(
+            TagsService::get_tag_rules(&state, root).unwrap()[0].tags,
+            vec!["sql"]
+        );
+    }
+
+    #[test]
+    fn tag_rules_are_saved_per_root() {
+        let dir = make_temp_dir("tag-rules-roots");
+        let (own, team) = (dir.join("own"), dir.join("team"));
+        fs::create_dir_all(&own).unwrap();
+        fs::create_dir_all(&team).unwrap();
+        fs::write(own.join("Query.txt"), "sql query").unwrap();
+        fs::write(team.join("Contract.txt"), "contract").unwrap();
+        let team_rules = r#"{"rules":[{"name":"legal","tags":["legal"],"body_regex":"contract"}]}"#;
+        fs::write(tag_rules_path(&team), team_rules).unwrap();
+        let (own_root, team_root) = (
+            own.to_string_lossy().to_string(),
+            team.to_string_lossy().to_string(),
+        );
+        let config = AppConfig {
+            prompts_dir: own_root.clone(),
+            prompt_roots: vec![crate::config::PromptRoot {
+                path: team_root.clone(),
+                label: "team".to_string(),
+                enabled: true,
+            }],
+            ..Default::default()
+        };
+        let state = Arc::new(AppState::new(config));
+
+        let rule = TagRule {
+            name: "sql".to_string(),
+            tags: vec!["db".to_string()],
+            body_regex: Some("sql".to_string()),
+            ..Default::default()
+        };
+        let prompts = TagsService::set_tag_rules(&state, own_root.clone(), vec![rule]).unwrap();
+        assert_eq!(
+            fs::read_to_string(tag_rules_path(&team)).unwrap(),
+            team_rules
+        );
+        let tags: Vec<(&str, Vec<String>)> = prompts
+            .iter()
+            .map(|prompt| (prompt.title.as_str(), prompt.tags.clone()))
+            .collect();
+        assert!(tags.contains(&("Query", vec!["db".to_string()])));
+        assert!(tags.contains(&("Contract", vec!["legal".to_string()])));
+        assert_eq!(
+            TagsService::get_tag_rules(&state, team_root).unwrap()[0].name,
+            "legal"
+        );
+        assert!(TagsService::get_tag_rules(&state, dir.to_string_lossy().to_string()).is_err());
+    }
+
+    #[test]
+    fn tag_info_is_stored_and_follows_renames() {
+        let dir = make_temp_dir("tag-info");
+        fs::write(dir.join("Reply #mail.txt"), "reply").unwrap();
+        let state = make_state(&dir);
+        PromptsService::refresh_prompts(&state);
+
+        let info = TagInfo {
+            color: Some("#f80".to_string()),
+            pinned: true,
+            ..Default::default()
+        };
+        let table = TagsService::set_tag_info(&state, "#Mail".to_string(), info.clone()).unwrap();
+        assert_eq!(table["mail"], info);
+        assert!(TagsService::set_tag_info(
+            &state,
+            "mail".to_string(),
+            TagInfo {
+                color: Some("red".to_string()),
+                ..Default::default()
+            }
+        )
+        .is_err());
+
+        let edit = build_edit(&state, &["mail".to_string()], Some("email")).unwrap();
+        apply_plan(plan_edit(&state, &edit, false).unwrap()).unwrap();
+        PromptsService::refresh_prompts(&state);
+        assert_eq!(TagsService::list_tag_info(&state)["email"], info);
+
+        let table = TagsService::delete_tag_info(&state, "email".to_string()).unwrap();
+        assert!(table.is_empty());
+    }
+
+    #[test]
+    fn set_policy_reindexes_with_new_rules() {
+        let dir = make_temp_dir("tag-policy");
+        fs::write(dir.join("Review #code_review #gpt-4.txt"), "review").unwrap();
+        let state = make_state(&dir);
+        let prompts = PromptsService::refresh_prompts(&state);
+        assert!(prompts[0].tags.is_empty());
+
+        let policy = TagPolicy {
+            max_chars: 16,
+            separators: "-_".to_string(),
+            ..Default::default()
+        };
+        let prompts = TagsService::set_policy(&state, policy.clone()).unwrap();
+        assert_eq!(prompts[0].tags, vec!["code_review", "gpt-4"]);
+        assert_eq!(TagsService::get_policy(&state), policy);
+        assert_eq!(load_tags_meta(&dir).unwrap().policy, policy);
+
+        let results = PromptsService::search(&state, "#gpt-4", 10, false, false).unwrap();
+        assert_eq!(results.len(), 1);
+    }
+
+    #[test]
+    fn library_settings_are_saved_to_the_first_root_only() {
+        let dir = make_temp_dir("tag-owner");
+        let (own, team) = (dir.join("own"), dir.join("team"));
+        fs::create_dir_all(&own).unwrap();
+        fs::create_dir_all(&team).unwrap();
+        fs::write(own.join("Reply #mail.txt"), "reply").unwrap();
+        let team_meta = r#"{"version":3,"tags_by_path":{},"aliases":{"law":"legal"}}"#;
+        fs::write(tags_meta_path(&team), team_meta).unwrap();
+        let config = AppConfig {
+            prompts_dir: own.to_string_lossy().to_string(),
+            prompt_roots: vec![crate::config::PromptRoot {
+                path: team.to_string_lossy().to_string(),
+                label: "team".to_string(),
+                enabled: true,
+            }],
+            ..Default::default()
+        };
+        let state = Arc::new(AppState::new(config));
+        PromptsService::refresh_prompts(&state);
+
+        TagsService::set_alias(&state, "mail".to_string(), "email".to_string()).unwrap();
+        let policy = TagPolicy {
+            separators: "-".to_string(),
+            ..Default::default()
+        };
+        TagsService::set_policy(&state, policy.clone()).unwrap();
+        TagsService::set_tag_info(&state, "email".to_string(), TagInfo::default()).unwrap();
+        assert_eq!(
+            fs::read_to_string(tags_meta_path(&team)).unwrap(),
+            team_meta
+        );
+        let meta = load_tags_meta(&own).unwrap();
+        assert_eq!(meta.aliases["mail"], "email");
+        assert_eq!(meta.policy, policy);
+        assert!(meta.tag_info.contains_key("email"));
+        assert_eq!(TagsService::list_aliases(&state)["law"], "legal");
+
+        // Settings read from another root are not rewritten from here.
+        assert!(TagsService::remove_alias(&state, "law".to_string()).is_err());
+        assert_eq!(
+            fs::read_to_string(tags_meta_path(&team)).unwrap(),
+            team_meta
+        );
+    }
+
+    #[test]
+    fn apply_plan_rolls_back_on_failure() {
+        let dir = make_temp_dir("tags-rollback");
+        fs::write(dir.join("a #x.txt"), "a").unwrap();
+        let rename = |from: &str, to: &str| FileRename {
+            from: dir.join(from).to_string_lossy().to_string(),
+            to: dir.join(to).to_string_lossy().to_string(),
+        };
+        let failed_rename = TagEditPlan {
+            prompts: Vec::new(),
+            renames: vec![
+                rename("a #x.txt", "a #y.txt"),
+                rename("missing.txt", "b.txt"),
+            ],
+            metas: Vec::new(),
+            rules: Vec::new(),
+        };
+        assert!(apply_plan(failed_rename).is_err());
+        assert!(dir.join("a #x.txt").exists());
+
+        // A file where a root should be makes the second save fail.
+        let not_a_dir = dir.join("not-a-dir");
+        fs::write(&not_a_dir, "").unwrap();
+        let failed_save = TagEditPlan {
+            prompts: Vec::new(),
+            renames: vec![rename("a #x.txt", "a #y.txt")],
+            metas: vec![
+                (dir.clone(), TagsMeta::default()),
+                (not_a_dir, TagsMeta::default()),
+            ],
+            rules: Vec::new(),
+        };
+        assert!(apply_plan(failed_save).is_err());
+        assert!(dir.join("a #x.txt").exists());
+        assert!(!dir.join("a #y.txt").exists());
+        assert!(!tags_meta_path(&dir).exists());
+    }
+}
@@ -1,8 +1,10 @@
-use crate::constants::{EVENT_LAUNCHER_SHOWN, MAIN_WINDOW_LABEL};
+use crate::config::SavedSearch;
+use crate::constants::{EVENT_LAUNCHER_SHOWN, EVENT_SAVED_SEARCH_TRIGGERED, MAIN_WINDOW_LABEL};
 use crate::lifecycle::GateDecision;
 use crate::state::AppState;
 #[cfg(target_os = "windows")]
 use crate::win;
+use std::collections::HashMap;
 use std::sync::Arc;
 use std::thread;
 use std::time::{Duration, Instant};
@@ -66,6 +68,54 @@ impl WindowService {
         Ok(())
     }
 
+    /// Registers the hotkeys of `searches` and drops the ones no longer bound.
+    /// A hotkey that fails to register is reported but does not stop the
+    /// others, so `saved_search_hotkeys` always matches what the OS holds.
+    pub fn sync_saved_search_hotkeys(
+        app: &AppHandle,
+        state: &Arc<AppState>,
+        searches: &[SavedSearch],
+    ) -> Result<(), String> {
+        let wanted: HashMap<String, String> = searches
+            .iter()
+            .filter_map(|search| {
+                let hotkey = search.hotkey.as_ref()?;
+                Some((hotkey.clone(), search.id.clone()))
+            })
+            .collect();
+        let mut registered = state.saved_search_hotkeys.lock().unwrap();
+        let mut errors = Vec::new();
+
+        for (hotkey, id) in &wanted {
+            match registered.get(hotkey) {
+                Some(current) if current == id => continue,
+                Some(_) => {
+                    let _ = app.global_shortcut().unregister(hotkey.as_str());
+                    registered.remove(hotkey);
+                }
+                None => {}
+            }
+            match register_saved_search_hotkey(app, state, hotkey, id) {
+                Ok(()) => {
+                    registered.insert(hotkey.clone(), id.clone());
+                }
+                Err(error) => errors.push(format!("{hotkey}: {error}")),
+            }
+        }
+        registered.retain(|hotkey, _| {
+            if wanted.contains_key(hotkey) {
+                return true;
+            }
+            let _ = app.global_shortcut().unregister(hotkey.as_str());
+            false
+        });
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(errors.join("; "))
+        }
+    }
+
     pub fn request_show(app: &AppHandle, state: &Arc<AppState>) -> Result<(), String> {
         let should_show = state.launcher_gate.lock().unwrap().request_show();
         if !should_show {
@@ -174,6 +224,24 @@ fn register_global_hotkey(
         .map_err(|e| format!("register hotkey failed: {e}"))
 }
 
+fn register_saved_search_hotkey(
+    app: &AppHandle,
+    state: &Arc<AppState>,
+    hotkey: &str,
+    id: &str,
+) -> Result<(), String> {
+    let state_handle = state.clone();
+    let id = id.to_string();
+    app.global_shortcut()
+        .on_shortcut(hotkey, move |app_handle, _, event| {
+            if event.state == ShortcutState::Pressed {
+                let _ = WindowService::request_show(app_handle, &state_handle);
+                let _ = app_handle.emit(EVENT_SAVED_SEARCH_TRIGGERED, id.clone());
+            }
+        })
+        .map_err(|e| format!("register hotkey failed: {e}"))
+}
+
 fn main_window(app: &AppHandle) -> Result<tauri::WebviewWindow, String> {
     app.get_webview_window(MAIN_WINDOW_LABEL)
         .ok_or_else(|| "main window not found".to_string())
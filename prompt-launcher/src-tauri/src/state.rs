@@ -1,17 +1,37 @@
+use crate::index_cache::IndexCache;
 use crate::lifecycle::LauncherGate;
 use crate::prompts::PromptEntry;
+use crate::search_index::SearchIndex;
+use crate::tag_policy::TagPolicy;
+use crate::tags_meta::TagInfo;
 use notify::RecommendedWatcher;
 use serde::Serialize;
 use std::collections::HashMap;
+use std::path::PathBuf;
 use std::sync::{Mutex, RwLock};
 
 pub struct AppState {
     pub(crate) prompts: RwLock<Vec<PromptEntry>>,
+    pub(crate) search_index: RwLock<SearchIndex>,
+    /// Tag alias -> canonical tag, merged from every root's `.tags.json`.
+    pub(crate) tag_aliases: RwLock<HashMap<String, String>>,
+    /// Tag policy of the primary root, used for queries and tag input.
+    pub(crate) tag_policy: RwLock<TagPolicy>,
+    /// Per-tag display metadata, merged like the aliases.
+    pub(crate) tag_info: RwLock<HashMap<String, TagInfo>>,
+    /// Entries of the last index pass and the file they are saved to, kept
+    /// current by refreshes and watcher re-reads. `None` keeps no cache.
+    pub(crate) index_cache: Mutex<Option<(PathBuf, IndexCache)>>,
+    /// Held by full refreshes and watcher re-reads from reading the files to
+    /// swapping in the result, so an older read never replaces a newer one.
+    pub(crate) index_pass: Mutex<()>,
     pub(crate) config: Mutex<crate::config::AppConfig>,
     pub(crate) watcher: Mutex<Option<RecommendedWatcher>>,
     pub(crate) last_active_hwnd: Mutex<Option<isize>>,
     pub(crate) pending_paths: Mutex<HashMap<String, u128>>,
     pub(crate) registered_hotkey: Mutex<Option<String>>,
+    /// Hotkey -> saved search id currently registered with the OS.
+    pub(crate) saved_search_hotkeys: Mutex<HashMap<String, String>>,
     pub(crate) launcher_gate: Mutex<LauncherGate>,
 }
 
@@ -21,16 +41,38 @@ pub struct RecentState {
     pub recent_meta: HashMap<String, i64>,
 }
 
+/// What a watcher batch changed: entries added or re-read, and the ids of
+/// entries that are gone.
+#[derive(Debug, Clone, Serialize)]
+pub struct PromptsDelta {
+    pub upserted: Vec<PromptEntry>,
+    pub removed: Vec<String>,
+}
+
 impl AppState {
     pub fn new(config: crate::config::AppConfig) -> Self {
         Self {
             prompts: RwLock::new(Vec::new()),
+            search_index: RwLock::new(SearchIndex::default()),
+            tag_aliases: RwLock::new(HashMap::new()),
+            tag_policy: RwLock::new(TagPolicy::default()),
+            tag_info: RwLock::new(HashMap::new()),
+            index_cache: Mutex::new(None),
+            index_pass: Mutex::new(()),
             config: Mutex::new(config),
             watcher: Mutex::new(None),
             last_active_hwnd: Mutex::new(None),
             pending_paths: Mutex::new(HashMap::new()),
             registered_hotkey: Mutex::new(None),
+            saved_search_hotkeys: Mutex::new(HashMap::new()),
             launcher_gate: Mutex::new(LauncherGate::new()),
         }
     }
+
+    /// Loads the index cache saved at `path`; refreshes save it back there.
+    pub fn with_index_cache(self, path: PathBuf) -> Self {
+        let cache = IndexCache::load(&path);
+        *self.index_cache.lock().unwrap() = Some((path, cache));
+        self
+    }
 }
@@ -0,0 +1,130 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::prompts::{normalize_text, PromptEntry};
+use crate::search_index::SearchIndex;
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TagCompletion {
+    pub tag: String,
+    /// Prompts carrying the tag or one of its children.
+    pub count: usize,
+    /// Sum of the frecency boosts of those prompts.
+    pub usage: i64,
+    /// The alias the input matched, when the tag was only reached through it.
+    pub alias: Option<String>,
+}
+
+/// Completes a partial `#tag` token. `input` may still carry its `#` or `-#`
+/// prefix. Results are ranked by usage, then count.
+pub fn complete_tags(
+    index: &SearchIndex,
+    prompts: &[PromptEntry],
+    input: &str,
+    aliases: &HashMap<String, String>,
+    boosts: &HashMap<String, i32>,
+    limit: usize,
+) -> Vec<TagCompletion> {
+    let trimmed = input.trim();
+    let token = trimmed.strip_prefix('-').unwrap_or(trimmed);
+    let prefix = normalize_text(token.strip_prefix('#').unwrap_or(token));
+
+    let mut matches: HashMap<&str, Option<&str>> = index
+        .tags_with_prefix(&prefix)
+        .map(|tag| (tag, None))
+        .collect();
+    for (alias, canonical) in aliases {
+        if alias.starts_with(&prefix) && !index.tag_docs(canonical).is_empty() {
+            matches.entry(canonical).or_insert(Some(alias));
+        }
+    }
+
+    let mut completions: Vec<TagCompletion> = matches
+        .into_iter()
+        .map(|(tag, alias)| {
+            let docs = index.tag_docs(tag);
+            let usage = docs
+                .iter()
+                .filter_map(|id| prompts.get(*id as usize))
+                .map(|prompt| boosts.get(&prompt.id).copied().unwrap_or(0) as i64)
+                .sum();
+            TagCompletion {
+                tag: tag.to_string(),
+                count: docs.len(),
+                usage,
+                alias: alias.map(str::to_string),
+            }
+        })
+        .collect();
+    completions.sort_by(|a, b| {
+        b.usage
+            .cmp(&a.usage)
+            .then_with(|| b.count.cmp(&a.count))
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    completions.truncate(limit);
+    completions
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn entry(id: &str, tags: &[&str]) -> PromptEntry {
+        PromptEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            body: String::new(),
+            preview: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            path: id.to_string(),
+            source: String::new(),
+        }
+    }
+
+    fn tags(completions: &[TagCompletion]) -> Vec<&str> {
+        completions.iter().map(|item| item.tag.as_str()).collect()
+    }
+
+    #[test]
+    fn completes_prefixes_segments_and_pinyin() {
+        let prompts = vec![
+            entry("a", &["email"]),
+            entry("b", &["email", "work/email"]),
+            entry("c", &["邮件"]),
+            entry("d", &["embed"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let boosts = HashMap::from([("d".to_string(), 10)]);
+        let aliases = HashMap::new();
+
+        let completions = complete_tags(&index, &prompts, "#Em", &aliases, &boosts, 10);
+        assert_eq!(tags(&completions), vec!["embed", "email", "work/email"]);
+        assert_eq!(completions[1].count, 2);
+        assert_eq!(completions[0].usage, 10);
+
+        let pinyin = complete_tags(&index, &prompts, "-#yj", &aliases, &boosts, 10);
+        assert_eq!(tags(&pinyin), vec!["邮件"]);
+        let full = complete_tags(&index, &prompts, "#youj", &aliases, &boosts, 10);
+        assert_eq!(tags(&full), vec!["邮件"]);
+        assert_eq!(
+            complete_tags(&index, &prompts, "#", &aliases, &boosts, 2).len(),
+            2
+        );
+    }
+
+    #[test]
+    fn completes_through_aliases() {
+        let prompts = vec![entry("a", &["email"])];
+        let index = SearchIndex::build(&prompts);
+        let aliases = HashMap::from([
+            ("mail".to_string(), "email".to_string()),
+            ("memo".to_string(), "note".to_string()),
+        ]);
+        let completions = complete_tags(&index, &prompts, "#ma", &aliases, &HashMap::new(), 10);
+        assert_eq!(completions.len(), 1);
+        assert_eq!(completions[0].tag, "email");
+        assert_eq!(completions[0].alias.as_deref(), Some("mail"));
+        assert!(complete_tags(&index, &prompts, "#me", &aliases, &HashMap::new(), 10).is_empty());
+    }
+}
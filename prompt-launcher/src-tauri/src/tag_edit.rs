@@ -0,0 +1,181 @@
+use crate::prompts::{tag_matches, TAG_SEPARATOR};
+use crate::tag_policy::TagPolicy;
+
+/// A library-wide tag change: every source tag (and its children) becomes
+/// `target`, or is dropped when there is no target.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct TagEdit {
+    pub sources: Vec<String>,
+    pub target: Option<String>,
+}
+
+impl TagEdit {
+    pub fn affects(&self, tag: &str) -> bool {
+        self.source_of(tag).is_some()
+    }
+
+    /// Applies the edit to a tag list, returning it sorted and deduplicated.
+    pub fn apply(&self, tags: &[String]) -> Vec<String> {
+        let mut next: Vec<String> = tags
+            .iter()
+            .filter_map(|tag| match self.source_of(tag) {
+                Some(source) => self.replace(tag, source),
+                None => Some(tag.clone()),
+            })
+            .collect();
+        next.sort();
+        next.dedup();
+        next
+    }
+
+    /// Rewrites `#tag` and `[tag]` markers in a file stem. Markers whose new
+    /// tag is nested are left alone since `/` cannot appear in a file name.
+    /// `#tag` markers are split on any whitespace, like the indexer does; when
+    /// the stem changes, each whitespace run shrinks to its first character
+    /// and a dropped marker takes the separator after it along.
+    pub fn rename_stem(&self, stem: &str, policy: &TagPolicy) -> String {
+        let mut out = String::new();
+        let mut rest = stem;
+        while let Some(start) = rest.find('[') {
+            let Some(end) = rest[start + 1..].find(']') else {
+                break;
+            };
+            let inner = &rest[start + 1..start + 1 + end];
+            out.push_str(&rest[..start]);
+            match self.rewrite_marker(inner, policy) {
+                Some(Some(tag)) => out.push_str(&format!("[{tag}]")),
+                Some(None) => {}
+                None => out.push_str(&rest[start..start + end + 2]),
+            }
+            rest = &rest[start + end + 2..];
+        }
+        out.push_str(rest);
+
+        let mut changed = out != stem;
+        let mut renamed = String::new();
+        // The separator in front of a dropped marker, used for the next token.
+        let mut held = None;
+        let mut rest = out.as_str();
+        while let Some(start) = rest.find(|c: char| !c.is_whitespace()) {
+            let end = rest[start..]
+                .find(char::is_whitespace)
+                .map_or(rest.len(), |end| start + end);
+            let (separator, token) = (&rest[..start], &rest[start..end]);
+            rest = &rest[end..];
+            let marker = token
+                .strip_prefix('#')
+                .and_then(|tag| self.rewrite_marker(tag, policy));
+            changed |= marker.is_some();
+            let token = match marker {
+                Some(Some(tag)) => format!("#{tag}"),
+                Some(None) => {
+                    held = held.or(separator.chars().next());
+                    continue;
+                }
+                None => token.to_string(),
+            };
+            let separator = held.take().or(separator.chars().next());
+            if let Some(first) = separator.filter(|_| !renamed.is_empty()) {
+                renamed.push(first);
+            }
+            renamed.push_str(&token);
+        }
+        if changed {
+            renamed
+        } else {
+            stem.to_string()
+        }
+    }
+
+    /// `None` when the marker is untouched, `Some(None)` when it is removed.
+    fn rewrite_marker(&self, raw: &str, policy: &TagPolicy) -> Option<Option<String>> {
+        let tag = policy.normalize(raw).ok()?;
+        let source = self.source_of(&tag)?;
+        match self.replace(&tag, source) {
+            Some(next) if next.contains(TAG_SEPARATOR) => None,
+            next => Some(next),
+        }
+    }
+
+    /// The longest source that `tag` equals or is nested under.
+    fn source_of(&self, tag: &str) -> Option<&str> {
+        self.sources
+            .iter()
+            .filter(|source| tag_matches(tag, source))
+            .max_by_key(|source| source.len())
+            .map(String::as_str)
+    }
+
+    fn replace(&self, tag: &str, source: &str) -> Option<String> {
+        self.target
+            .as_ref()
+            .map(|target| format!("{target}{}", &tag[source.len()..]))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn edit(sources: &[&str], target: Option<&str>) -> TagEdit {
+        TagEdit {
+            sources: sources.iter().map(|tag| tag.to_string()).collect(),
+            target: target.map(str::to_string),
+        }
+    }
+
+    fn tags(values: &[&str]) -> Vec<String> {
+        values.iter().map(|tag| tag.to_string()).collect()
+    }
+
+    #[test]
+    fn apply_renames_merges_and_deletes_subtrees() {
+        let rename = edit(&["work"], Some("office"));
+        assert_eq!(
+            rename.apply(&tags(&["work/email", "workshop", "work"])),
+            tags(&["office", "office/email", "workshop"])
+        );
+
+        let merge = edit(&["mail", "邮件"], Some("email"));
+        assert_eq!(
+            merge.apply(&tags(&["mail", "邮件", "email"])),
+            tags(&["email"])
+        );
+
+        let delete = edit(&["draft"], None);
+        assert_eq!(delete.apply(&tags(&["draft/old", "a"])), tags(&["a"]));
+        assert!(!delete.affects("drafts"));
+    }
+
+    #[test]
+    fn rename_stem_rewrites_markers() {
+        let policy = TagPolicy::default();
+        let rename = edit(&["mail"], Some("email"));
+        assert_eq!(
+            rename.rename_stem("回复 #Mail #work", &policy),
+            "回复 #email #work"
+        );
+        assert_eq!(rename.rename_stem("[mail]回复", &policy), "[email]回复");
+        assert_eq!(
+            rename.rename_stem("mail #mailbox", &policy),
+            "mail #mailbox"
+        );
+
+        let delete = edit(&["mail"], None);
+        assert_eq!(
+            delete.rename_stem("回复 #mail [mail] 模板", &policy),
+            "回复 模板"
+        );
+
+        // Full-width and other whitespace separate markers too.
+        assert_eq!(rename.rename_stem("回复　#mail", &policy), "回复　#email");
+        assert_eq!(
+            delete.rename_stem("回复　#mail\t模板", &policy),
+            "回复　模板"
+        );
+        assert_eq!(delete.rename_stem("回复　 模板", &policy), "回复　 模板");
+
+        let nested = edit(&["mail"], Some("work/mail"));
+        assert_eq!(nested.rename_stem("回复 #mail", &policy), "回复 #mail");
+    }
+}
@@ -0,0 +1,212 @@
+use serde::{Deserialize, Serialize};
+use std::sync::LazyLock;
+use unicode_normalization::UnicodeNormalization;
+
+use crate::prompts::{normalize_text, TAG_MAX_DEPTH, TAG_SEPARATOR};
+
+const POLICY_MAX_CHARS_LIMIT: usize = 64;
+// Characters the query and filename syntax already use around tags.
+const RESERVED_SEPARATORS: &[char] = &['/', '#', '[', ']', '"'];
+
+static DEFAULT_POLICY: LazyLock<TagPolicy> = LazyLock::new(TagPolicy::default);
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "snake_case")]
+pub enum TagCharClass {
+    AsciiAlnum,
+    /// CJK Unified Ideographs, U+4E00–U+9FFF.
+    Cjk,
+    /// Hiragana and katakana.
+    Kana,
+    Hangul,
+    /// Any Unicode letter.
+    Letter,
+    /// Any Unicode digit.
+    Digit,
+}
+
+impl TagCharClass {
+    fn contains(self, ch: char) -> bool {
+        match self {
+            Self::AsciiAlnum => ch.is_ascii_alphanumeric(),
+            Self::Cjk => matches!(ch, '\u{4E00}'..='\u{9FFF}'),
+            Self::Kana => matches!(ch, '\u{3040}'..='\u{30FF}' | '\u{31F0}'..='\u{31FF}'),
+            Self::Hangul => matches!(
+                ch,
+                '\u{1100}'..='\u{11FF}' | '\u{3130}'..='\u{318F}' | '\u{AC00}'..='\u{D7AF}'
+            ),
+            Self::Letter => ch.is_alphabetic(),
+            Self::Digit => ch.is_numeric(),
+        }
+    }
+}
+
+/// Which tags a library accepts. Stored in `.tags.json`; the defaults match
+/// the original rules (1-10 ASCII letters, digits or CJK, lowercased).
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct TagPolicy {
+    /// Maximum characters per `/` segment.
+    pub max_chars: usize,
+    pub char_classes: Vec<TagCharClass>,
+    /// Extra characters allowed inside a segment, e.g. `-_` for `gpt-4`.
+    pub separators: String,
+    pub case_fold: bool,
+}
+
+impl Default for TagPolicy {
+    fn default() -> Self {
+        Self {
+            max_chars: 10,
+            char_classes: vec![TagCharClass::AsciiAlnum, TagCharClass::Cjk],
+            separators: String::new(),
+            case_fold: true,
+        }
+    }
+}
+
+/// Why a tag was rejected.
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum TagRejection {
+    Empty,
+    TooLong { max: usize },
+    TooDeep { max: usize },
+    InvalidChar(char),
+    EdgeSeparator(char),
+}
+
+impl TagRejection {
+    pub fn describe(&self, token: &str) -> String {
+        let reason = match self {
+            Self::Empty => "标签或其中一级为空".to_string(),
+            Self::TooLong { max } => format!("每级最多 {max} 个字符"),
+            Self::TooDeep { max } => format!("最多 {max} 级"),
+            Self::InvalidChar(ch) => format!("不允许字符「{ch}」"),
+            Self::EdgeSeparator(ch) => format!("分隔符「{ch}」不能位于开头或结尾"),
+        };
+        format!("标签「{token}」无效：{reason}")
+    }
+}
+
+impl TagPolicy {
+    /// Normalizes a possibly nested tag (`work/email`).
+    pub fn normalize(&self, raw: &str) -> Result<String, TagRejection> {
+        let segments: Vec<String> = raw
+            .trim()
+            .split(TAG_SEPARATOR)
+            .map(|segment| self.normalize_segment(segment))
+            .collect::<Result<_, _>>()?;
+        if segments.len() > TAG_MAX_DEPTH {
+            return Err(TagRejection::TooDeep { max: TAG_MAX_DEPTH });
+        }
+        Ok(segments.join(TAG_SEPARATOR))
+    }
+
+    pub fn normalize_segment(&self, raw: &str) -> Result<String, TagRejection> {
+        let folded = if self.case_fold {
+            normalize_text(raw)
+        } else {
+            raw.nfkc().collect()
+        };
+        let segment = folded.trim();
+        if segment.is_empty() {
+            return Err(TagRejection::Empty);
+        }
+        if segment.chars().count() > self.max_chars {
+            return Err(TagRejection::TooLong {
+                max: self.max_chars,
+            });
+        }
+        if let Some(ch) = segment.chars().find(|ch| !self.allows(*ch)) {
+            return Err(TagRejection::InvalidChar(ch));
+        }
+        for edge in [segment.chars().next(), segment.chars().last()]
+            .into_iter()
+            .flatten()
+        {
+            if self.separators.contains(edge) {
+                return Err(TagRejection::EdgeSeparator(edge));
+            }
+        }
+        Ok(segment.to_string())
+    }
+
+    /// Checks a user-supplied policy before it is saved.
+    pub fn validate(&self) -> Result<(), String> {
+        if self.max_chars == 0 || self.max_chars > POLICY_MAX_CHARS_LIMIT {
+            return Err(format!(
+                "标签长度上限需在 1-{POLICY_MAX_CHARS_LIMIT} 之间"
+            ));
+        }
+        if self.char_classes.is_empty() {
+            return Err("至少需要允许一类字符".to_string());
+        }
+        if let Some(ch) = self
+            .separators
+            .chars()
+            .find(|ch| ch.is_whitespace() || RESERVED_SEPARATORS.contains(ch))
+        {
+            return Err(format!("分隔符不能包含「{ch}」"));
+        }
+        Ok(())
+    }
+
+    fn allows(&self, ch: char) -> bool {
+        self.char_classes.iter().any(|class| class.contains(ch)) || self.separators.contains(ch)
+    }
+}
+
+pub fn default_policy() -> &'static TagPolicy {
+    &DEFAULT_POLICY
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn custom_policy_accepts_separators_and_scripts() {
+        let policy = TagPolicy {
+            max_chars: 12,
+            char_classes: vec![
+                TagCharClass::AsciiAlnum,
+                TagCharClass::Cjk,
+                TagCharClass::Kana,
+                TagCharClass::Hangul,
+            ],
+            separators: "-_".to_string(),
+            case_fold: true,
+        };
+        assert_eq!(policy.normalize("GPT-4"), Ok("gpt-4".to_string()));
+        assert_eq!(policy.normalize("code_review"), Ok("code_review".to_string()));
+        assert_eq!(policy.normalize("メール/한국어"), Ok("メール/한국어".to_string()));
+        assert_eq!(policy.normalize("-draft"), Err(TagRejection::EdgeSeparator('-')));
+
+        let keep_case = TagPolicy {
+            case_fold: false,
+            ..Default::default()
+        };
+        assert_eq!(keep_case.normalize("ＧＰＴ"), Ok("GPT".to_string()));
+    }
+
+    #[test]
+    fn default_policy_reports_rejection_reason() {
+        let policy = TagPolicy::default();
+        assert_eq!(policy.normalize("gpt-4"), Err(TagRejection::InvalidChar('-')));
+        assert_eq!(
+            policy.normalize("abcdefghijk"),
+            Err(TagRejection::TooLong { max: 10 })
+        );
+        assert_eq!(policy.normalize("a//b"), Err(TagRejection::Empty));
+        assert_eq!(
+            TagRejection::InvalidChar('-').describe("gpt-4"),
+            "标签「gpt-4」无效：不允许字符「-」"
+        );
+        assert!(TagPolicy {
+            separators: "/".to_string(),
+            ..Default::default()
+        }
+        .validate()
+        .is_err());
+    }
+}
@@ -0,0 +1,370 @@
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+use crate::regex_search::compile_regex;
+use crate::tag_policy::TagPolicy;
+use crate::tags_meta::{folder_keys, path_to_key};
+
+pub const TAG_RULES_FILENAME: &str = ".tag-rules.json";
+pub const TAG_RULES_VERSION: u32 = 1;
+const TAG_RULES_MAX: usize = 200;
+
+/// Adds `tags` to every prompt that meets all of the rule's conditions.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TagRule {
+    pub name: String,
+    pub tags: Vec<String>,
+    /// Case-insensitive regex on the body.
+    pub body_regex: Option<String>,
+    /// Glob on the path relative to the root. `*` and `?` stay within one
+    /// folder, `**` spans folders, and a match on a folder covers everything
+    /// beneath it, so `clients/*` tags `clients/acme/brief.md`.
+    pub path_glob: Option<String>,
+    /// File extension without the dot, e.g. `md`.
+    pub extension: Option<String>,
+    pub front_matter: Option<FrontMatterMatch>,
+}
+
+/// A front-matter field that must be present and, when `value` is set, equal
+/// it (or contain it, for `[a, b]` lists).
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct FrontMatterMatch {
+    pub key: String,
+    pub value: Option<String>,
+}
+
+#[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TagRulesFile {
+    pub version: u32,
+    pub rules: Vec<TagRule>,
+}
+
+impl Default for TagRulesFile {
+    fn default() -> Self {
+        Self {
+            version: TAG_RULES_VERSION,
+            rules: Vec::new(),
+        }
+    }
+}
+
+pub fn tag_rules_path(root: &Path) -> PathBuf {
+    root.join(TAG_RULES_FILENAME)
+}
+
+pub fn load_tag_rules(root: &Path) -> Result<TagRulesFile, String> {
+    let path = tag_rules_path(root);
+    if !path.exists() {
+        return Ok(TagRulesFile::default());
+    }
+    let data = fs::read_to_string(&path).map_err(|e| format!("read tag rules failed: {e}"))?;
+    let file: TagRulesFile =
+        serde_json::from_str(&data).map_err(|e| format!("parse tag rules failed: {e}"))?;
+    if file.version > TAG_RULES_VERSION {
+        return Err(format!("标签规则版本 {} 过新，请升级应用", file.version));
+    }
+    Ok(file)
+}
+
+pub fn save_tag_rules(root: &Path, file: &TagRulesFile) -> Result<(), String> {
+    let data = serde_json::to_string_pretty(file)
+        .map_err(|e| format!("serialize tag rules failed: {e}"))?;
+    fs::write(tag_rules_path(root), data).map_err(|e| format!("write tag rules failed: {e}"))
+}
+
+/// Checks user-supplied rules before they are saved and normalizes their tags,
+/// so library-wide tag edits can find them later.
+pub fn validate_rules(rules: &mut [TagRule], policy: &TagPolicy) -> Result<(), String> {
+    if rules.len() > TAG_RULES_MAX {
+        return Err(format!("标签规则最多 {TAG_RULES_MAX} 条"));
+    }
+    for rule in rules.iter_mut() {
+        if rule.tags.is_empty() {
+            return Err(format!("规则「{}」未设置标签", rule.name));
+        }
+        let mut tags = Vec::new();
+        for tag in &rule.tags {
+            let tag = policy
+                .normalize(tag)
+                .map_err(|reason| reason.describe(tag))?;
+            if !tags.contains(&tag) {
+                tags.push(tag);
+            }
+        }
+        rule.tags = tags;
+        if rule
+            .front_matter
+            .as_ref()
+            .is_some_and(|field| field.key.trim().is_empty())
+        {
+            return Err(format!("规则「{}」的 front matter 字段名为空", rule.name));
+        }
+    }
+    TagRules::compile(rules).map(|_| ())
+}
+
+/// Compiled rules for one root.
+#[derive(Debug, Default)]
+pub struct TagRules {
+    rules: Vec<CompiledRule>,
+}
+
+#[derive(Debug)]
+struct CompiledRule {
+    tags: Vec<String>,
+    body: Option<Regex>,
+    path: Option<String>,
+    extension: Option<String>,
+    front_matter: Option<FrontMatterMatch>,
+}
+
+impl TagRules {
+    pub fn compile(rules: &[TagRule]) -> Result<Self, String> {
+        let mut compiled = Vec::new();
+        for rule in rules {
+            let body = match rule.body_regex.as_deref() {
+                Some(pattern) => Some(
+                    compile_regex(pattern)
+                        .map_err(|err| format!("规则「{}」：{err}", rule.name))?,
+                ),
+                None => None,
+            };
+            let path = rule
+                .path_glob
+                .as_deref()
+                .map(|glob| glob.trim().trim_matches('/').to_lowercase())
+                .filter(|glob| !glob.is_empty());
+            let extension = rule
+                .extension
+                .as_deref()
+                .map(|ext| ext.trim().trim_start_matches('.').to_lowercase())
+                .filter(|ext| !ext.is_empty());
+            let front_matter = rule.front_matter.clone();
+            if body.is_none() && path.is_none() && extension.is_none() && front_matter.is_none() {
+                return Err(format!("规则「{}」至少需要一个条件", rule.name));
+            }
+            compiled.push(CompiledRule {
+                tags: rule.tags.clone(),
+                body,
+                path,
+                extension,
+                front_matter,
+            });
+        }
+        Ok(Self { rules: compiled })
+    }
+
+    /// Raw tags from every rule that matches the prompt, in rule order.
+    pub fn tags_for(&self, root: &Path, path: &Path, body: &str) -> Vec<String> {
+        if self.rules.is_empty() {
+            return Vec::new();
+        }
+        let key = path_to_key(root, path).to_lowercase();
+        let extension = path
+            .extension()
+            .map(|ext| ext.to_string_lossy().to_lowercase())
+            .unwrap_or_default();
+        let fields = front_matter(body);
+        let mut tags = Vec::new();
+        for rule in &self.rules {
+            if rule.matches(&key, &extension, body, &fields) {
+                tags.extend(rule.tags.iter().cloned());
+            }
+        }
+        tags
+    }
+}
+
+impl CompiledRule {
+    fn matches(
+        &self,
+        key: &str,
+        extension: &str,
+        body: &str,
+        fields: &HashMap<String, String>,
+    ) -> bool {
+        if self
+            .extension
+            .as_deref()
+            .is_some_and(|ext| ext != extension)
+        {
+            return false;
+        }
+        if let Some(glob) = &self.path {
+            let mut candidates = folder_keys(key)
+                .map(|folder| folder.trim_end_matches('/'))
+                .chain(std::iter::once(key));
+            if !candidates.any(|candidate| glob_match(glob, candidate)) {
+                return false;
+            }
+        }
+        if let Some(field) = &self.front_matter {
+            let Some(found) = fields.get(&field.key.trim().to_lowercase()) else {
+                return false;
+            };
+            if let Some(expected) = &field.value {
+                if !front_matter_values(found)
+                    .any(|value| value.eq_ignore_ascii_case(expected.trim()))
+                {
+                    return false;
+                }
+            }
+        }
+        self.body.as_ref().is_none_or(|regex| regex.is_match(body))
+    }
+}
+
+/// `key: value` lines of a leading `---` block; keys are lowercased.
+pub fn front_matter(body: &str) -> HashMap<String, String> {
+    let mut fields = HashMap::new();
+    let mut lines = body.trim_start_matches('\u{feff}').lines();
+    if lines.next().map(str::trim_end) != Some("---") {
+        return fields;
+    }
+    for line in lines {
+        if line.trim_end() == "---" {
+            return fields;
+        }
+        if let Some((key, value)) = line.split_once(':') {
+            let key = key.trim();
+            if !key.is_empty() && !key.starts_with('#') {
+                fields.insert(key.to_lowercase(), value.trim().to_string());
+            }
+        }
+    }
+    // An unterminated block is body text, not front matter.
+    HashMap::new()
+}
+
+fn front_matter_values(raw: &str) -> impl Iterator<Item = &str> {
+    let inner = raw
+        .strip_prefix('[')
+        .and_then(|rest| rest.strip_suffix(']'))
+        .unwrap_or(raw);
+    inner
+        .split(',')
+        .map(|value| value.trim().trim_matches(|ch| ch == '"' || ch == '\''))
+        .filter(|value| !value.is_empty())
+}
+
+fn glob_match(pattern: &str, text: &str) -> bool {
+    if let Some(rest) = pattern.strip_prefix("**") {
+        if let Some(after) = rest.strip_prefix('/') {
+            if glob_match(after, text) {
+                return true;
+            }
+        }
+        return text
+            .char_indices()
+            .map(|(index, _)| index)
+            .chain(std::iter::once(text.len()))
+            .any(|index| glob_match(rest, &text[index..]));
+    }
+    let mut chars = pattern.chars();
+    match chars.next() {
+        None => text.is_empty(),
+        Some('*') => {
+            let rest = chars.as_str();
+            let segment_end = text.find('/').unwrap_or(text.len());
+            text[..segment_end]
+                .char_indices()
+                .map(|(index, _)| index)
+                .chain(std::iter::once(segment_end))
+                .any(|index| glob_match(rest, &text[index..]))
+        }
+        Some('?') => {
+            let mut text_chars = text.chars();
+            matches!(text_chars.next(), Some(ch) if ch != '/')
+                && glob_match(chars.as_str(), text_chars.as_str())
+        }
+        Some(expected) => {
+            let mut text_chars = text.chars();
+            text_chars.next() == Some(expected) && glob_match(chars.as_str(), text_chars.as_str())
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rule(tags: &[&str]) -> TagRule {
+        TagRule {
+            name: "test".to_string(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn rules_match_body_path_extension_and_front_matter() {
+        let root = Path::new("/lib");
+        let rules = TagRules::compile(&[
+            TagRule {
+                body_regex: Some("\\bsql\\b".to_string()),
+                ..rule(&["db"])
+            },
+            TagRule {
+                path_glob: Some("clients/*".to_string()),
+                ..rule(&["client"])
+            },
+            TagRule {
+                extension: Some(".MD".to_string()),
+                front_matter: Some(FrontMatterMatch {
+                    key: "Lang".to_string(),
+                    value: Some("en".to_string()),
+                }),
+                ..rule(&["english"])
+            },
+        ])
+        .unwrap();
+
+        let sql = root.join("notes").join("query.txt");
+        assert_eq!(rules.tags_for(root, &sql, "Write SQL for me"), vec!["db"]);
+        assert!(rules.tags_for(root, &sql, "mysqldump").is_empty());
+
+        let brief = root.join("Clients").join("acme").join("brief.txt");
+        assert_eq!(rules.tags_for(root, &brief, ""), vec!["client"]);
+        assert!(rules
+            .tags_for(root, &root.join("clients.txt"), "")
+            .is_empty());
+
+        let body = "---\nlang: [zh, EN]\n---\nhello";
+        assert_eq!(
+            rules.tags_for(root, &root.join("a.md"), body),
+            vec!["english"]
+        );
+        assert!(rules.tags_for(root, &root.join("a.txt"), body).is_empty());
+        assert!(rules
+            .tags_for(root, &root.join("a.md"), "---\nlang: en\nhello")
+            .is_empty());
+    }
+
+    #[test]
+    fn glob_supports_double_star() {
+        assert!(glob_match("**/draft?.txt", "draft1.txt"));
+        assert!(glob_match("**/draft?.txt", "a/b/draft1.txt"));
+        assert!(glob_match("team/**/legal", "team/x/y/legal"));
+        assert!(!glob_match("team/*/legal", "team/x/y/legal"));
+    }
+
+    #[test]
+    fn validate_rejects_rules_without_conditions() {
+        let policy = TagPolicy::default();
+        assert_eq!(
+            validate_rules(&mut [rule(&["db"])], &policy).unwrap_err(),
+            "规则「test」至少需要一个条件"
+        );
+        let bad_tag = TagRule {
+            extension: Some("md".to_string()),
+            ..rule(&["gpt-4"])
+        };
+        assert!(validate_rules(&mut [bad_tag], &policy).is_err());
+    }
+}
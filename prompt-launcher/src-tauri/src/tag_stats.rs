@@ -0,0 +1,166 @@
+use serde::Serialize;
+use std::collections::HashMap;
+
+use crate::prompts::PromptEntry;
+
+const MS_PER_HOUR: f64 = 3_600_000.0;
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TagStat {
+    pub tag: String,
+    pub count: usize,
+    /// Sum over the tagged prompts of their recency weight: 1.0 for a prompt
+    /// used just now, halving every half-life, 0 for prompts never used.
+    pub usage: f64,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq, Eq)]
+pub struct TagPair {
+    pub a: String,
+    pub b: String,
+    pub count: usize,
+}
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TagStats {
+    /// Number of prompts the stats were computed over.
+    pub prompts: usize,
+    pub tags: Vec<TagStat>,
+    pub pairs: Vec<TagPair>,
+}
+
+/// Counts tags over the prompts at `ids`. Tags are ranked by count, then
+/// usage; pairs by count. Both lists are cut to `limit`.
+pub fn tag_stats(
+    prompts: &[PromptEntry],
+    ids: &[usize],
+    recent_meta: &HashMap<String, i64>,
+    half_life_hours: u32,
+    now_ms: i64,
+    limit: usize,
+) -> TagStats {
+    let half_life = half_life_hours.max(1) as f64;
+    let mut tags: HashMap<&str, (usize, f64)> = HashMap::new();
+    let mut pairs: HashMap<(&str, &str), usize> = HashMap::new();
+
+    for prompt in ids.iter().filter_map(|id| prompts.get(*id)) {
+        let usage = recent_meta
+            .get(&prompt.id)
+            .map(|last_used| {
+                let age_hours = now_ms.saturating_sub(*last_used).max(0) as f64 / MS_PER_HOUR;
+                0.5f64.powf(age_hours / half_life)
+            })
+            .unwrap_or(0.0);
+        for (index, tag) in prompt.tags.iter().enumerate() {
+            let entry = tags.entry(tag.as_str()).or_insert((0, 0.0));
+            entry.0 += 1;
+            entry.1 += usage;
+            for other in &prompt.tags[index + 1..] {
+                let key = if tag < other {
+                    (tag.as_str(), other.as_str())
+                } else {
+                    (other.as_str(), tag.as_str())
+                };
+                *pairs.entry(key).or_insert(0) += 1;
+            }
+        }
+    }
+
+    let mut tags: Vec<TagStat> = tags
+        .into_iter()
+        .map(|(tag, (count, usage))| TagStat {
+            tag: tag.to_string(),
+            count,
+            usage,
+        })
+        .collect();
+    tags.sort_by(|a, b| {
+        b.count
+            .cmp(&a.count)
+            .then_with(|| b.usage.total_cmp(&a.usage))
+            .then_with(|| a.tag.cmp(&b.tag))
+    });
+    tags.truncate(limit);
+
+    let mut pairs: Vec<TagPair> = pairs
+        .into_iter()
+        .map(|((a, b), count)| TagPair {
+            a: a.to_string(),
+            b: b.to_string(),
+            count,
+        })
+        .collect();
+    pairs.sort_by(|x, y| {
+        y.count
+            .cmp(&x.count)
+            .then_with(|| (&x.a, &x.b).cmp(&(&y.a, &y.b)))
+    });
+    pairs.truncate(limit);
+
+    TagStats {
+        prompts: ids.len(),
+        tags,
+        pairs,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const HOUR_MS: i64 = 3_600_000;
+
+    fn entry(id: &str, tags: &[&str]) -> PromptEntry {
+        PromptEntry {
+            id: id.to_string(),
+            title: id.to_string(),
+            body: String::new(),
+            preview: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            path: id.to_string(),
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn tag_stats_counts_usage_and_pairs() {
+        let prompts = vec![
+            entry("a", &["email", "work"]),
+            entry("b", &["work", "email"]),
+            entry("c", &["draft"]),
+            entry("d", &["work"]),
+        ];
+        let now = 100 * HOUR_MS;
+        let recent: HashMap<String, i64> = [
+            ("a".to_string(), now),
+            ("c".to_string(), now - 72 * HOUR_MS),
+        ]
+        .into_iter()
+        .collect();
+
+        let stats = tag_stats(&prompts, &[0, 1, 2, 3], &recent, 72, now, 10);
+        assert_eq!(stats.prompts, 4);
+        let counts: Vec<(&str, usize, f64)> = stats
+            .tags
+            .iter()
+            .map(|stat| (stat.tag.as_str(), stat.count, stat.usage))
+            .collect();
+        assert_eq!(
+            counts,
+            vec![("work", 3, 1.0), ("email", 2, 1.0), ("draft", 1, 0.5)]
+        );
+        assert_eq!(
+            stats.pairs,
+            vec![TagPair {
+                a: "email".to_string(),
+                b: "work".to_string(),
+                count: 2,
+            }]
+        );
+
+        let subset = tag_stats(&prompts, &[2, 3], &recent, 72, now, 1);
+        assert_eq!(subset.prompts, 2);
+        assert_eq!(subset.tags.len(), 1);
+        assert_eq!(subset.tags[0].tag, "draft");
+    }
+}
@@ -0,0 +1,280 @@
+use serde::Serialize;
+use std::collections::{HashMap, HashSet};
+
+use crate::prompts::{tag_matches, PromptEntry};
+use crate::search_index::IndexedDoc;
+use crate::tag_policy::TagPolicy;
+use crate::tags_meta::canonical_tag;
+
+const KEYWORD_WEIGHT: f64 = 0.4;
+const SIMILAR_WEIGHT: f64 = 0.6;
+/// Keywords that do not yet name a tag in the library are discounted, so an
+/// established tag wins over a new word of the same weight.
+const NEW_KEYWORD_FACTOR: f64 = 0.5;
+const NEIGHBOR_LIMIT: usize = 10;
+const STOPWORDS: &[&str] = &[
+    "a", "an", "and", "are", "as", "at", "be", "by", "for", "from", "in", "is", "it", "of", "on",
+    "or", "that", "the", "this", "to", "was", "with", "you", "your", "的", "了", "是", "在", "和",
+    "我", "你", "请",
+];
+
+#[derive(Debug, Clone, Serialize, PartialEq)]
+pub struct TagSuggestion {
+    pub tag: String,
+    pub score: f64,
+    /// TF-IDF weight of the tag as a keyword of the body, 0-1.
+    pub keyword_score: f64,
+    /// Similarity-weighted share of similar prompts carrying the tag, 0-1.
+    pub similar_score: f64,
+}
+
+/// TF-IDF term vectors of the indexed prompts, aligned with the search index.
+/// Built once per index, so suggestions do not re-tokenize the library on
+/// every call.
+#[derive(Debug, Default)]
+pub struct TermVectors {
+    vectors: Vec<HashMap<String, f64>>,
+    norms: Vec<f64>,
+}
+
+impl TermVectors {
+    pub fn build(docs: &[IndexedDoc]) -> Self {
+        let counts: Vec<HashMap<String, usize>> = docs.iter().map(term_counts).collect();
+        let mut doc_freq: HashMap<&str, usize> = HashMap::new();
+        for terms in &counts {
+            for term in terms.keys() {
+                *doc_freq.entry(term.as_str()).or_default() += 1;
+            }
+        }
+        let total = docs.len() as f64;
+        let idf = |term: &str| {
+            let df = doc_freq.get(term).copied().unwrap_or(0) as f64;
+            ((1.0 + total) / (1.0 + df)).ln() + 1.0
+        };
+        let vectors: Vec<HashMap<String, f64>> = counts
+            .iter()
+            .map(|terms| {
+                let len = terms.values().sum::<usize>().max(1) as f64;
+                terms
+                    .iter()
+                    .map(|(term, count)| (term.clone(), *count as f64 / len * idf(term)))
+                    .collect()
+            })
+            .collect();
+        let norms = vectors
+            .iter()
+            .map(|vector| vector.values().map(|w| w * w).sum::<f64>().sqrt())
+            .collect();
+        Self { vectors, norms }
+    }
+
+    fn cosine(&self, a: usize, b: usize) -> f64 {
+        let dot: f64 = self.vectors[a]
+            .iter()
+            .filter_map(|(term, weight)| self.vectors[b].get(term).map(|other| weight * other))
+            .sum();
+        if dot == 0.0 {
+            return 0.0;
+        }
+        dot / (self.norms[a] * self.norms[b])
+    }
+}
+
+/// Proposes tags for `prompts[target]` from its body keywords and from the
+/// tags of the most similar prompts. Tags the prompt already has (or has a
+/// child of) are skipped, and every suggestion passes `policy`. `vectors`
+/// must be built from the same prompt list.
+pub fn suggest_tags(
+    prompts: &[PromptEntry],
+    vectors: &TermVectors,
+    target: usize,
+    policy: &TagPolicy,
+    aliases: &HashMap<String, String>,
+    limit: usize,
+) -> Vec<TagSuggestion> {
+    let (Some(prompt), Some(own)) = (prompts.get(target), vectors.vectors.get(target)) else {
+        return Vec::new();
+    };
+    let known: HashSet<&str> = prompts
+        .iter()
+        .flat_map(|prompt| prompt.tags.iter().map(String::as_str))
+        .collect();
+
+    let max_weight = own.values().copied().fold(0.0, f64::max);
+    let mut keyword: HashMap<String, f64> = HashMap::new();
+    for (term, weight) in own {
+        if term.chars().all(|ch| ch.is_numeric()) {
+            continue;
+        }
+        let Ok(tag) = policy.normalize(term) else {
+            continue;
+        };
+        let tag = canonical_tag(aliases, &tag);
+        let factor = if known.contains(tag.as_str()) {
+            1.0
+        } else {
+            NEW_KEYWORD_FACTOR
+        };
+        let entry = keyword.entry(tag).or_default();
+        *entry = entry.max(weight / max_weight * factor);
+    }
+
+    let mut neighbors: Vec<(usize, f64)> = (0..vectors.vectors.len())
+        .filter(|id| *id != target)
+        .map(|id| (id, vectors.cosine(target, id)))
+        .filter(|(_, similarity)| *similarity > 0.0)
+        .collect();
+    neighbors.sort_by(|a, b| b.1.total_cmp(&a.1).then(a.0.cmp(&b.0)));
+    neighbors.truncate(NEIGHBOR_LIMIT);
+    let similarity_sum: f64 = neighbors.iter().map(|(_, similarity)| similarity).sum();
+    let mut similar: HashMap<String, f64> = HashMap::new();
+    for (id, similarity) in &neighbors {
+        for tag in &prompts[*id].tags {
+            *similar.entry(tag.clone()).or_default() += similarity / similarity_sum;
+        }
+    }
+
+    let candidates: HashSet<&String> = keyword.keys().chain(similar.keys()).collect();
+    let mut suggestions: Vec<TagSuggestion> = candidates
+        .into_iter()
+        .filter(|tag| !prompt.tags.iter().any(|own| tag_matches(own, tag)))
+        .filter(|tag| policy.normalize(tag).is_ok())
+        .map(|tag| {
+            let keyword_score = keyword.get(tag).copied().unwrap_or(0.0);
+            let similar_score = similar.get(tag).copied().unwrap_or(0.0);
+            TagSuggestion {
+                tag: tag.clone(),
+                score: KEYWORD_WEIGHT * keyword_score + SIMILAR_WEIGHT * similar_score,
+                keyword_score,
+                similar_score,
+            }
+        })
+        .collect();
+    suggestions.sort_by(|a, b| b.score.total_cmp(&a.score).then_with(|| a.tag.cmp(&b.tag)));
+    suggestions.truncate(limit);
+    suggestions
+}
+
+/// Words of the normalized title and body: alphanumeric runs for spaced
+/// scripts and character bigrams for CJK, which has no spaces to split on.
+fn term_counts(doc: &IndexedDoc) -> HashMap<String, usize> {
+    let mut counts = HashMap::new();
+    let runs = [doc.title.as_str(), doc.body()]
+        .into_iter()
+        .flat_map(|text| text.split(|ch: char| !ch.is_alphanumeric()));
+    for run in runs {
+        let mut word = String::new();
+        let mut cjk: Vec<char> = Vec::new();
+        for ch in run.chars() {
+            if is_cjk(ch) {
+                cjk.push(ch);
+                add_word(&mut counts, &mut word);
+            } else {
+                add_bigrams(&mut counts, &mut cjk);
+                word.push(ch);
+            }
+        }
+        add_word(&mut counts, &mut word);
+        add_bigrams(&mut counts, &mut cjk);
+    }
+    counts
+}
+
+fn add_word(counts: &mut HashMap<String, usize>, word: &mut String) {
+    if word.chars().nth(1).is_some() && !STOPWORDS.contains(&word.as_str()) {
+        *counts.entry(word.clone()).or_default() += 1;
+    }
+    word.clear();
+}
+
+fn add_bigrams(counts: &mut HashMap<String, usize>, run: &mut Vec<char>) {
+    for pair in run.windows(2) {
+        if pair
+            .iter()
+            .any(|ch| STOPWORDS.contains(&ch.to_string().as_str()))
+        {
+            continue;
+        }
+        *counts.entry(pair.iter().collect()).or_default() += 1;
+    }
+    run.clear();
+}
+
+fn is_cjk(ch: char) -> bool {
+    matches!(ch, '\u{4E00}'..='\u{9FFF}')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::search_index::SearchIndex;
+
+    fn prompt(title: &str, body: &str, tags: &[&str]) -> PromptEntry {
+        PromptEntry {
+            id: title.to_string(),
+            title: title.to_string(),
+            body: body.to_string(),
+            preview: String::new(),
+            tags: tags.iter().map(|tag| tag.to_string()).collect(),
+            path: title.to_string(),
+            source: String::new(),
+        }
+    }
+
+    #[test]
+    fn suggests_keywords_and_tags_of_similar_prompts() {
+        let prompts = vec![
+            prompt("Draft", "Write a polite email reply about the invoice", &[]),
+            prompt(
+                "Reply",
+                "Polite email reply to a customer",
+                &["mail", "work"],
+            ),
+            prompt("Invoice", "Explain the invoice total", &["invoice"]),
+            prompt("Poem", "写一首关于春天的诗", &["诗歌"]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let vectors = index.term_vectors();
+        let suggestions = suggest_tags(
+            &prompts,
+            vectors,
+            0,
+            &TagPolicy::default(),
+            &HashMap::new(),
+            5,
+        );
+        let tags: Vec<&str> = suggestions.iter().map(|item| item.tag.as_str()).collect();
+        // `invoice` is both a body keyword and a neighbour's tag.
+        assert_eq!(&tags[..3], &["invoice", "mail", "work"]);
+        assert!(suggestions[0].keyword_score > 0.0 && suggestions[0].similar_score > 0.0);
+        assert!(!tags.contains(&"诗歌"));
+        assert!(suggestions.iter().all(|item| item.tag != "the"));
+
+        let poem = suggest_tags(
+            &prompts,
+            vectors,
+            3,
+            &TagPolicy::default(),
+            &HashMap::new(),
+            10,
+        );
+        assert!(poem.iter().any(|item| item.tag == "春天"));
+        assert!(poem.iter().all(|item| item.tag != "诗歌"));
+    }
+
+    #[test]
+    fn suggestions_respect_aliases_and_existing_children() {
+        let prompts = vec![
+            prompt("A", "email follow up", &["email/reply"]),
+            prompt("B", "mail follow up", &[]),
+        ];
+        let index = SearchIndex::build(&prompts);
+        let vectors = index.term_vectors();
+        let aliases = HashMap::from([("mail".to_string(), "email".to_string())]);
+        let a = suggest_tags(&prompts, vectors, 0, &TagPolicy::default(), &aliases, 10);
+        assert!(a.iter().all(|item| item.tag != "email"));
+        let b = suggest_tags(&prompts, vectors, 1, &TagPolicy::default(), &aliases, 10);
+        assert_eq!(b[0].tag, "email/reply");
+        assert!(b.iter().all(|item| item.tag != "mail"));
+    }
+}
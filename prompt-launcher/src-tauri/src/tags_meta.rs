@@ -4,28 +4,67 @@ use std::fs;
 use std::path::{Path, PathBuf};
 use std::time::{SystemTime, UNIX_EPOCH};
 
+use crate::prompts::{tag_matches, tag_prefixes};
+use crate::tag_policy::TagPolicy;
+
 pub const TAGS_META_FILENAME: &str = ".tags.json";
+/// v1: `tags_by_path` only. v2: adds the per-tag `tag_info` table. v3: adds
+/// `removed_by_path`.
+pub const TAGS_META_VERSION: u32 = 3;
+const TAG_DESCRIPTION_MAX_CHARS: usize = 200;
+const TAG_ICON_MAX_CHARS: usize = 8;
+
+/// Display metadata for one tag.
+#[derive(Debug, Clone, Default, Serialize, Deserialize, PartialEq, Eq)]
+#[serde(default)]
+pub struct TagInfo {
+    /// `#RGB` or `#RRGGBB`.
+    pub color: Option<String>,
+    pub description: String,
+    /// Emoji or short icon name.
+    pub icon: Option<String>,
+    /// Pinned tags are always shown in the tag bar.
+    pub pinned: bool,
+    /// Ascending order among pinned tags.
+    pub sort_order: i32,
+}
 
 #[derive(Debug, Clone, Serialize, Deserialize, PartialEq, Eq)]
 pub struct TagsMeta {
-    #[serde(default = "default_version")]
+    #[serde(default = "legacy_version")]
     pub version: u32,
     #[serde(default)]
     pub updated_at: i64,
     pub tags_by_path: HashMap<String, Vec<String>>,
+    /// Tags a folder entry or tag rule would give a prompt but that were
+    /// removed from it, keyed like `tags_by_path`.
+    #[serde(default)]
+    pub removed_by_path: HashMap<String, Vec<String>>,
+    /// Alias -> canonical tag. Kept flat: a canonical tag is never an alias.
+    #[serde(default)]
+    pub aliases: HashMap<String, String>,
+    #[serde(default)]
+    pub policy: TagPolicy,
+    #[serde(default)]
+    pub tag_info: HashMap<String, TagInfo>,
 }
 
 impl Default for TagsMeta {
     fn default() -> Self {
         Self {
-            version: default_version(),
+            version: TAGS_META_VERSION,
             updated_at: 0,
             tags_by_path: HashMap::new(),
+            removed_by_path: HashMap::new(),
+            aliases: HashMap::new(),
+            policy: TagPolicy::default(),
+            tag_info: HashMap::new(),
         }
     }
 }
 
-fn default_version() -> u32 {
+// Files written before the version field existed are v1.
+fn legacy_version() -> u32 {
     1
 }
 
@@ -33,23 +72,73 @@ pub fn tags_meta_path(root: &Path) -> PathBuf {
     root.join(TAGS_META_FILENAME)
 }
 
+/// Loads a root's metadata for editing. Fails for files written by a newer
+/// version, which must not be saved over.
 pub fn load_tags_meta(root: &Path) -> Result<TagsMeta, String> {
+    let mut meta = read_tags_meta(root)?;
+    migrate(&mut meta)?;
+    Ok(meta)
+}
+
+/// Loads a root's metadata for indexing only. A file from a newer version is
+/// read as far as this schema goes and keeps its version, so
+/// `save_tags_meta` refuses to write it back.
+pub fn read_tags_meta(root: &Path) -> Result<TagsMeta, String> {
     let path = tags_meta_path(root);
     if !path.exists() {
         return Ok(TagsMeta::default());
     }
-    let data = fs::read_to_string(&path)
-        .map_err(|e| format!("read tags meta failed: {e}"))?;
-    let meta: TagsMeta = serde_json::from_str(&data)
-        .map_err(|e| format!("parse tags meta failed: {e}"))?;
-    Ok(meta)
+    let data = fs::read_to_string(&path).map_err(|e| format!("read tags meta failed: {e}"))?;
+    serde_json::from_str(&data).map_err(|e| format!("parse tags meta failed: {e}"))
+}
+
+pub fn is_newer_version(meta: &TagsMeta) -> bool {
+    meta.version > TAGS_META_VERSION
+}
+
+/// Upgrades an older schema in place; the next save writes the new version.
+fn migrate(meta: &mut TagsMeta) -> Result<(), String> {
+    if is_newer_version(meta) {
+        return Err(newer_version_error(meta));
+    }
+    // v1 -> v2 -> v3 only add `tag_info` and `removed_by_path`, which
+    // deserialize as empty.
+    meta.version = TAGS_META_VERSION;
+    Ok(())
+}
+
+fn newer_version_error(meta: &TagsMeta) -> String {
+    format!("标签元数据版本 {} 过新，请升级应用", meta.version)
+}
+
+pub fn validate_tag_info(info: &TagInfo) -> Result<(), String> {
+    if let Some(color) = &info.color {
+        let hex = color.strip_prefix('#').unwrap_or_default();
+        let valid = matches!(hex.len(), 3 | 6) && hex.chars().all(|ch| ch.is_ascii_hexdigit());
+        if !valid {
+            return Err(format!("颜色格式无效：{color}"));
+        }
+    }
+    if info.description.chars().count() > TAG_DESCRIPTION_MAX_CHARS {
+        return Err(format!("描述最多 {TAG_DESCRIPTION_MAX_CHARS} 个字符"));
+    }
+    if info
+        .icon
+        .as_ref()
+        .is_some_and(|icon| icon.trim().is_empty() || icon.chars().count() > TAG_ICON_MAX_CHARS)
+    {
+        return Err(format!("图标需为 1-{TAG_ICON_MAX_CHARS} 个字符"));
+    }
+    Ok(())
 }
 
 pub fn save_tags_meta(root: &Path, meta: &TagsMeta) -> Result<(), String> {
+    if is_newer_version(meta) {
+        return Err(newer_version_error(meta));
+    }
     let path = tags_meta_path(root);
     if let Some(parent) = path.parent() {
-        fs::create_dir_all(parent)
-            .map_err(|e| format!("create tags meta dir failed: {e}"))?;
+        fs::create_dir_all(parent).map_err(|e| format!("create tags meta dir failed: {e}"))?;
     }
     let data = serde_json::to_string_pretty(meta)
         .map_err(|e| format!("serialize tags meta failed: {e}"))?;
@@ -64,10 +153,25 @@ pub fn resolve_tags_for_path(
     fallback: Vec<String>,
 ) -> Vec<String> {
     let key = path_to_key(root, path);
-    if let Some(tags) = meta.tags_by_path.get(&key) {
-        return tags.clone();
+    let mut tags = meta.tags_by_path.get(&key).cloned().unwrap_or(fallback);
+    for folder in folder_keys(&key) {
+        let Some(inherited) = meta.tags_by_path.get(folder) else {
+            continue;
+        };
+        for tag in inherited {
+            if !tags.contains(tag) {
+                tags.push(tag.clone());
+            }
+        }
     }
-    fallback
+    tags
+}
+
+/// Keys of the folders above a prompt key, outermost first:
+/// `team/legal/a.txt` yields `team/` and `team/legal/`. Tags stored under a
+/// folder key are inherited by every prompt beneath it.
+pub fn folder_keys(key: &str) -> impl Iterator<Item = &str> {
+    key.match_indices('/').map(move |(end, _)| &key[..=end])
 }
 
 pub fn path_to_key(root: &Path, path: &Path) -> String {
@@ -79,6 +183,80 @@ pub fn path_to_key(root: &Path, path: &Path) -> String {
         .join("/")
 }
 
+/// Rewrites `tag` through the alias map. The longest aliased ancestor wins, so
+/// with `mail -> email` the tag `mail/reply` becomes `email/reply`.
+pub fn canonical_tag(aliases: &HashMap<String, String>, tag: &str) -> String {
+    let prefixes: Vec<&str> = tag_prefixes(tag).collect();
+    for prefix in prefixes.into_iter().rev() {
+        if let Some(canonical) = aliases.get(prefix) {
+            return format!("{canonical}{}", &tag[prefix.len()..]);
+        }
+    }
+    tag.to_string()
+}
+
+/// Adds `alias -> canonical` (both already normalized), pointing existing
+/// aliases of `alias` or one of its child tags at the new canonical tag so the
+/// map stays flat: with `mail -> work/mail`, aliasing `work` to `job` makes it
+/// `mail -> job/mail`.
+pub fn set_alias(meta: &mut TagsMeta, alias: &str, canonical: &str) -> Result<(), String> {
+    let canonical = canonical_tag(&meta.aliases, canonical);
+    if tag_matches(&canonical, alias) {
+        return Err("别名不能指向自身或其子标签".to_string());
+    }
+    for target in meta.aliases.values_mut() {
+        if tag_matches(target, alias) {
+            *target = format!("{canonical}{}", &target[alias.len()..]);
+        }
+    }
+    meta.aliases.insert(alias.to_string(), canonical);
+    Ok(())
+}
+
+/// Canonicalizes every entry of `tags_by_path` and `removed_by_path` through
+/// `aliases`, returning how many changed.
+pub fn merge_aliases(meta: &mut TagsMeta, aliases: &HashMap<String, String>) -> usize {
+    let mut changed = 0;
+    let entries = meta
+        .tags_by_path
+        .values_mut()
+        .chain(meta.removed_by_path.values_mut());
+    for tags in entries {
+        let mut merged: Vec<String> = tags.iter().map(|tag| canonical_tag(aliases, tag)).collect();
+        merged.sort();
+        merged.dedup();
+        if *tags != merged {
+            *tags = merged;
+            changed += 1;
+        }
+    }
+    changed
+}
+
+/// Library-wide settings merged from every root: the union of aliases and tag
+/// info (earlier roots win on conflicts) and the first root's policy, which
+/// every root is indexed and edited with. The first root owns these settings:
+/// edits are saved there and other roots' files are only read. `tags_by_path`
+/// is left empty.
+pub fn load_library_meta(roots: &[PathBuf]) -> TagsMeta {
+    let mut library = TagsMeta::default();
+    for (position, root) in roots.iter().enumerate() {
+        let Ok(meta) = read_tags_meta(root) else {
+            continue;
+        };
+        if position == 0 {
+            library.policy = meta.policy;
+        }
+        for (alias, canonical) in meta.aliases {
+            library.aliases.entry(alias).or_insert(canonical);
+        }
+        for (tag, info) in meta.tag_info {
+            library.tag_info.entry(tag).or_insert(info);
+        }
+    }
+    library
+}
+
 pub fn touch_updated_at(meta: &mut TagsMeta) {
     let now = SystemTime::now()
         .duration_since(UNIX_EPOCH)
@@ -115,7 +293,7 @@ mod tests {
     fn save_then_load_roundtrip() {
         let dir = make_temp_dir("tags-meta-roundtrip");
         let mut meta = TagsMeta::default();
-        meta.version = 1;
+        meta.version = TAGS_META_VERSION;
         meta.updated_at = 123;
         meta.tags_by_path
             .insert("foo.txt".to_string(), vec!["tag1".to_string()]);
@@ -133,18 +311,126 @@ mod tests {
             .insert("foo.txt".to_string(), vec!["a".to_string()]);
 
         let path = dir.join("foo.txt");
-        let resolved =
-            resolve_tags_for_path(&meta, &dir, &path, vec!["b".to_string()]);
+        let resolved = resolve_tags_for_path(&meta, &dir, &path, vec!["b".to_string()]);
         assert_eq!(resolved, vec!["a".to_string()]);
     }
 
+    #[test]
+    fn resolve_inherits_folder_tags() {
+        let dir = make_temp_dir("tags-meta-inherit");
+        let mut meta = TagsMeta::default();
+        meta.tags_by_path
+            .insert("team/".to_string(), vec!["team".to_string()]);
+        meta.tags_by_path.insert(
+            "team/legal/".to_string(),
+            vec!["legal".to_string(), "team".to_string()],
+        );
+        meta.tags_by_path
+            .insert("team/legal/nda.txt".to_string(), vec!["nda".to_string()]);
+
+        let nda = dir.join("team").join("legal").join("nda.txt");
+        let resolved = resolve_tags_for_path(&meta, &dir, &nda, vec!["b".to_string()]);
+        assert_eq!(resolved, vec!["nda", "team", "legal"]);
+
+        let memo = dir.join("team").join("memo.txt");
+        let resolved = resolve_tags_for_path(&meta, &dir, &memo, vec!["memo".to_string()]);
+        assert_eq!(resolved, vec!["memo", "team"]);
+
+        let other = dir.join("teams").join("a.txt");
+        let resolved = resolve_tags_for_path(&meta, &dir, &other, Vec::new());
+        assert!(resolved.is_empty());
+    }
+
+    #[test]
+    fn load_migrates_v1_files() {
+        let dir = make_temp_dir("tags-meta-v1");
+        fs::write(
+            tags_meta_path(&dir),
+            r#"{"version":1,"updated_at":5,"tags_by_path":{"a.txt":["x"]}}"#,
+        )
+        .unwrap();
+        let meta = load_tags_meta(&dir).expect("load v1");
+        assert_eq!(meta.version, TAGS_META_VERSION);
+        assert_eq!(meta.tags_by_path["a.txt"], vec!["x".to_string()]);
+        assert!(meta.tag_info.is_empty());
+
+        fs::write(tags_meta_path(&dir), r#"{"version":99,"tags_by_path":{}}"#).unwrap();
+        assert!(load_tags_meta(&dir).unwrap_err().contains("过新"));
+    }
+
+    #[test]
+    fn newer_files_are_read_only() {
+        let dir = make_temp_dir("tags-meta-newer");
+        let data = r#"{"version":99,"tags_by_path":{"a.txt":["x"]},"future":true}"#;
+        fs::write(tags_meta_path(&dir), data).unwrap();
+
+        let meta = read_tags_meta(&dir).expect("read newer file");
+        assert_eq!(meta.tags_by_path["a.txt"], vec!["x".to_string()]);
+        assert!(save_tags_meta(&dir, &meta).unwrap_err().contains("过新"));
+        assert_eq!(fs::read_to_string(tags_meta_path(&dir)).unwrap(), data);
+    }
+
+    #[test]
+    fn validate_tag_info_checks_fields() {
+        let mut info = TagInfo {
+            color: Some("#1e90ff".to_string()),
+            icon: Some("📧".to_string()),
+            ..Default::default()
+        };
+        assert!(validate_tag_info(&info).is_ok());
+        info.color = Some("blue".to_string());
+        assert!(validate_tag_info(&info).is_err());
+    }
+
+    #[test]
+    fn canonical_tag_rewrites_aliased_ancestors() {
+        let mut meta = TagsMeta::default();
+        set_alias(&mut meta, "邮件", "email").unwrap();
+        set_alias(&mut meta, "mail", "邮件").unwrap();
+        assert_eq!(meta.aliases["mail"], "email");
+        assert_eq!(canonical_tag(&meta.aliases, "邮件/回复"), "email/回复");
+        assert_eq!(canonical_tag(&meta.aliases, "mailbox"), "mailbox");
+
+        set_alias(&mut meta, "email", "inbox").unwrap();
+        assert_eq!(meta.aliases["邮件"], "inbox");
+        assert!(set_alias(&mut meta, "inbox", "邮件").is_err());
+        assert!(set_alias(&mut meta, "work", "work/email").is_err());
+    }
+
+    #[test]
+    fn set_alias_repoints_aliases_of_child_tags() {
+        let mut meta = TagsMeta::default();
+        set_alias(&mut meta, "mail", "work/mail").unwrap();
+        set_alias(&mut meta, "workout", "sport").unwrap();
+        set_alias(&mut meta, "work", "job").unwrap();
+        assert_eq!(meta.aliases["mail"], "job/mail");
+        assert_eq!(meta.aliases["workout"], "sport");
+        assert_eq!(canonical_tag(&meta.aliases, "mail/reply"), "job/mail/reply");
+    }
+
+    #[test]
+    fn merge_aliases_rewrites_tags_by_path() {
+        let mut meta = TagsMeta::default();
+        meta.tags_by_path.insert(
+            "a.txt".to_string(),
+            vec!["email".to_string(), "邮件".to_string()],
+        );
+        meta.tags_by_path
+            .insert("b.txt".to_string(), vec!["work".to_string()]);
+        set_alias(&mut meta, "邮件", "email").unwrap();
+
+        let aliases = meta.aliases.clone();
+        assert_eq!(merge_aliases(&mut meta, &aliases), 1);
+        assert_eq!(meta.tags_by_path["a.txt"], vec!["email".to_string()]);
+        assert_eq!(merge_aliases(&mut meta, &aliases), 0);
+    }
+
     #[test]
     fn resolve_uses_fallback_when_missing() {
         let dir = make_temp_dir("tags-meta-fallback");
         let meta = TagsMeta::default();
         let path = dir.join("bar.txt");
-        let resolved =
-            resolve_tags_for_path(&meta, &dir, &path, vec!["b".to_string()]);
+        let resolved = resolve_tags_for_path(&meta, &dir, &path, vec!["b".to_string()]);
         assert_eq!(resolved, vec!["b".to_string()]);
     }
 }
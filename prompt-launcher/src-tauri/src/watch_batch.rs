@@ -0,0 +1,139 @@
+use std::collections::HashSet;
+use std::path::{Path, PathBuf};
+
+use crate::tag_rules::TAG_RULES_FILENAME;
+use crate::tags_meta::TAGS_META_FILENAME;
+
+/// Batches touching more paths than this are rescanned in full.
+const RESCAN_THRESHOLD: usize = 256;
+const IGNORED_DIRS: &[&str] = &[".git", ".hg", ".svn"];
+// Editor swap, backup and probe files plus OS litter.
+const IGNORED_NAMES: &[&str] = &[".ds_store", "thumbs.db", "4913"];
+const IGNORED_SUFFIXES: &[&str] = &[
+    ".swp", ".swo", ".swx", ".tmp", ".temp", ".part", ".bak", "~",
+];
+
+/// Watcher events gathered during one quiet period, reduced to the distinct
+/// paths that can affect the library.
+#[derive(Debug, Default)]
+pub struct WatchBatch {
+    paths: Vec<PathBuf>,
+    seen: HashSet<PathBuf>,
+    overflow: bool,
+}
+
+impl WatchBatch {
+    pub fn push(&mut self, event: notify::Event) {
+        self.overflow |= event.need_rescan();
+        for path in event.paths {
+            if !is_ignored_path(&path) && self.seen.insert(path.clone()) {
+                self.paths.push(path);
+            }
+        }
+    }
+
+    /// Records a watcher error: events may have been lost, so the batch falls
+    /// back to a full rescan.
+    pub fn mark_overflow(&mut self) {
+        self.overflow = true;
+    }
+
+    /// True when every event was for ignored files.
+    pub fn is_empty(&self) -> bool {
+        !self.overflow && self.paths.is_empty()
+    }
+
+    pub fn paths(&self) -> &[PathBuf] {
+        &self.paths
+    }
+
+    /// Whether the batch needs a full rescan instead of per-path updates: the
+    /// backend dropped events, the batch is too large to be worth patching (a
+    /// folder move or checkout), or library metadata that affects every
+    /// prompt changed.
+    pub fn needs_rescan(&self) -> bool {
+        self.overflow
+            || self.paths.len() > RESCAN_THRESHOLD
+            || self.paths.iter().any(|path| {
+                path.file_name()
+                    .is_some_and(|name| name == TAGS_META_FILENAME || name == TAG_RULES_FILENAME)
+            })
+    }
+}
+
+/// Paths whose changes never affect the library: VCS internals, editor swap
+/// and backup files, and temp copies of the metadata files.
+pub fn is_ignored_path(path: &Path) -> bool {
+    if path
+        .components()
+        .any(|component| IGNORED_DIRS.iter().any(|dir| component.as_os_str() == *dir))
+    {
+        return true;
+    }
+    let Some(name) = path
+        .file_name()
+        .map(|name| name.to_string_lossy().to_lowercase())
+    else {
+        return false;
+    };
+    let metadata_temp = [TAGS_META_FILENAME, TAG_RULES_FILENAME]
+        .iter()
+        .any(|file| name.starts_with(file) && name != *file);
+    metadata_temp
+        || IGNORED_NAMES.contains(&name.as_str())
+        || IGNORED_SUFFIXES.iter().any(|suffix| name.ends_with(suffix))
+        || name.starts_with(".#")
+        || (name.len() > 1 && name.starts_with('#') && name.ends_with('#'))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn event(paths: &[&str]) -> notify::Event {
+        paths
+            .iter()
+            .fold(notify::Event::new(notify::EventKind::Any), |event, path| {
+                event.add_path(PathBuf::from(path))
+            })
+    }
+
+    #[test]
+    fn batch_dedupes_and_drops_ignored_paths() {
+        let mut batch = WatchBatch::default();
+        batch.push(event(&["/lib/a.txt", "/lib/.a.txt.swp", "/lib/a.txt~"]));
+        batch.push(event(&[
+            "/lib/a.txt",
+            "/lib/.git/index",
+            "/lib/.tags.json.tmp",
+        ]));
+        assert_eq!(batch.paths(), &[PathBuf::from("/lib/a.txt")]);
+        assert!(!batch.needs_rescan());
+
+        let mut ignored = WatchBatch::default();
+        ignored.push(event(&["/lib/4913", "/lib/.#a.txt", "/lib/#a.txt#"]));
+        assert!(ignored.is_empty());
+    }
+
+    #[test]
+    fn batch_rescans_on_overflow_and_metadata_changes() {
+        let mut meta = WatchBatch::default();
+        meta.push(event(&["/lib/a.txt", "/lib/.tags.json"]));
+        assert!(meta.needs_rescan());
+
+        let mut rules = WatchBatch::default();
+        rules.push(event(&["/lib/.tag-rules.json"]));
+        assert!(rules.needs_rescan());
+
+        let mut overflow = WatchBatch::default();
+        overflow
+            .push(notify::Event::new(notify::EventKind::Any).set_flag(notify::event::Flag::Rescan));
+        assert!(!overflow.is_empty());
+        assert!(overflow.needs_rescan());
+
+        let mut error = WatchBatch::default();
+        error.mark_overflow();
+        assert!(!error.is_empty());
+        assert!(error.needs_rescan());
+    }
+}
@@ -1,4 +1,6 @@
 export const EVENTS = {
   LAUNCHER_SHOWN: "launcher-shown",
-  PROMPTS_UPDATED: "prompts-updated"
+  PROMPTS_UPDATED: "prompts-updated",
+  PROMPTS_CHANGED: "prompts-changed",
+  SAVED_SEARCH_TRIGGERED: "saved-search-triggered"
 } as const;
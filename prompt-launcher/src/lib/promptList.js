@@ -1,5 +1,6 @@
 /**
  * @typedef {import("./types").PromptEntry} PromptEntry
+ * @typedef {import("./types").TagInfo} TagInfo
  */
 
 /**
@@ -60,3 +61,27 @@ export function buildTopTags(prompts, limit) {
     .slice(0, limit)
     .map(([tag, count]) => ({ tag, count }));
 }
+
+/**
+ * Pinned tags first (by sort_order, then name), followed by the top `limit`
+ * of the remaining counted tags. Pinned tags ignore the limit.
+ * @param {{ tag: string; count: number }[]} counts sorted by count
+ * @param {number} limit
+ * @param {Record<string, TagInfo>} tagInfo
+ * @returns {{ tag: string; count: number }[]}
+ */
+export function withPinnedTags(counts, limit, tagInfo) {
+  const countMap = new Map(counts.map((entry) => [entry.tag, entry.count]));
+  const pinned = Object.entries(tagInfo ?? {})
+    .filter(([, info]) => info.pinned)
+    .sort((a, b) => {
+      if (a[1].sort_order !== b[1].sort_order) {
+        return a[1].sort_order - b[1].sort_order;
+      }
+      return a[0].localeCompare(b[0]);
+    })
+    .map(([tag]) => ({ tag, count: countMap.get(tag) ?? 0 }));
+  const pinnedSet = new Set(pinned.map((entry) => entry.tag));
+  const rest = counts.filter((entry) => !pinnedSet.has(entry.tag)).slice(0, limit);
+  return [...pinned, ...rest];
+}
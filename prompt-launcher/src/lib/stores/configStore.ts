@@ -1,6 +1,11 @@
 import { get, writable } from "svelte/store";
 import { tauriClient } from "$lib/tauriClient";
-import type { AppConfig, RecentState } from "$lib/types";
+import type {
+  AppConfig,
+  PromptRoot,
+  RecentState,
+  SavedSearch
+} from "$lib/types";
 
 const defaultConfig: AppConfig = {
   prompts_dir: "",
@@ -15,7 +20,13 @@ const defaultConfig: AppConfig = {
   top_tags_use_results: false,
   top_tags_limit: 8,
   show_shortcuts_hint: true,
-  preview_chars: 50
+  preview_chars: 50,
+  use_counts: {},
+  frecency_weight: 100,
+  frecency_half_life_hours: 72,
+  saved_searches: [],
+  prompt_roots: [],
+  watch_quiet_ms: 300
 };
 
 const store = writable<AppConfig>(defaultConfig);
@@ -43,6 +54,9 @@ export const configStore = {
   setPromptsDirSync: (path: string) => {
     setLocal({ prompts_dir: path });
   },
+  setPromptRootsSync: (roots: PromptRoot[]) => {
+    setLocal({ prompt_roots: roots });
+  },
   setAutoPaste: async (value: boolean) => {
     setLocal({ auto_paste: value });
     await tauriClient.setAutoPaste(value);
@@ -82,6 +96,7 @@ export const configStore = {
   clearRecent: async () => {
     const recentState = await tauriClient.clearRecent();
     applyRecentState(recentState);
+    setLocal({ use_counts: {} });
     return recentState;
   },
   setTopTagsScope: async (value: boolean) => {
@@ -99,5 +114,51 @@ export const configStore = {
   setShowShortcutsHint: async (value: boolean) => {
     setLocal({ show_shortcuts_hint: value });
     await tauriClient.setShowShortcutsHint(value);
+  },
+  setFrecency: async (weight: number, halfLifeHours: number) => {
+    setLocal({
+      frecency_weight: weight,
+      frecency_half_life_hours: halfLifeHours
+    });
+    await tauriClient.setFrecency(weight, halfLifeHours);
+  },
+  setWatchQuietMs: async (value: number) => {
+    setLocal({ watch_quiet_ms: value });
+    await tauriClient.setWatchQuietMs(value);
+  },
+  createSavedSearch: async (
+    name: string,
+    query: string,
+    favoritesOnly: boolean,
+    hotkey: string | null,
+    regex = false
+  ) => {
+    const search = await tauriClient.createSavedSearch(
+      name,
+      query,
+      favoritesOnly,
+      hotkey,
+      regex
+    );
+    store.update((current) => ({
+      ...current,
+      saved_searches: [...current.saved_searches, search]
+    }));
+    return search;
+  },
+  updateSavedSearch: async (search: SavedSearch) => {
+    const updated = await tauriClient.updateSavedSearch(search);
+    store.update((current) => ({
+      ...current,
+      saved_searches: current.saved_searches.map((item) =>
+        item.id === updated.id ? updated : item
+      )
+    }));
+    return updated;
+  },
+  deleteSavedSearch: async (id: string) => {
+    const searches = await tauriClient.deleteSavedSearch(id);
+    setLocal({ saved_searches: searches ?? [] });
+    return searches;
   }
 };
@@ -1,6 +1,6 @@
 import { writable } from "svelte/store";
 import { tauriClient } from "$lib/tauriClient";
-import type { PromptEntry } from "$lib/types";
+import type { PromptEntry, PromptRoot, PromptsDelta } from "$lib/types";
 
 const store = writable<PromptEntry[]>([]);
 
@@ -16,11 +16,32 @@ export const promptsStore = {
   setFromEvent: (prompts: PromptEntry[]) => {
     store.set(prompts ?? []);
   },
+  applyDelta: (delta: PromptsDelta) => {
+    store.update((prompts) => {
+      const removed = new Set(delta.removed);
+      const upserted = new Map<string, PromptEntry>(
+        delta.upserted.map((prompt) => [prompt.id, prompt])
+      );
+      const next = prompts
+        .filter((prompt) => !removed.has(prompt.id))
+        .map((prompt) => {
+          const updated = upserted.get(prompt.id);
+          upserted.delete(prompt.id);
+          return updated ?? prompt;
+        });
+      return [...next, ...upserted.values()];
+    });
+  },
   setPromptsDir: async (path: string) => {
     const prompts = await tauriClient.setPromptsDir(path);
     store.set(prompts ?? []);
     return prompts ?? [];
   },
+  setPromptRoots: async (roots: PromptRoot[]) => {
+    const prompts = await tauriClient.setPromptRoots(roots);
+    store.set(prompts ?? []);
+    return prompts ?? [];
+  },
   deletePromptFiles: async (paths: string[]) => {
     const prompts = await tauriClient.deletePromptFiles(paths);
     store.set(prompts ?? []);
@@ -35,7 +56,35 @@ export const promptsStore = {
     store.set(prompts ?? []);
     return prompts ?? [];
   },
-  createPromptFile: (name: string) => tauriClient.createPromptFile(name),
-  searchPrompts: (query: string, limit: number, favoritesOnly: boolean) =>
-    tauriClient.searchPrompts(query, limit, favoritesOnly)
+  setTagAlias: async (alias: string, canonical: string) => {
+    const prompts = await tauriClient.setTagAlias(alias, canonical);
+    store.set(prompts ?? []);
+    return prompts ?? [];
+  },
+  removeTagAlias: async (alias: string) => {
+    const prompts = await tauriClient.removeTagAlias(alias);
+    store.set(prompts ?? []);
+    return prompts ?? [];
+  },
+  mergeTagAliases: async () => {
+    const prompts = await tauriClient.mergeTagAliases();
+    store.set(prompts ?? []);
+    return prompts ?? [];
+  },
+  createPromptFile: (name: string, root: string | null = null) =>
+    tauriClient.createPromptFile(name, root),
+  searchPrompts: (
+    query: string,
+    limit: number,
+    favoritesOnly: boolean,
+    regex = false
+  ) => tauriClient.searchPrompts(query, limit, favoritesOnly, regex),
+  searchPromptsPage: (
+    query: string,
+    offset: number,
+    limit: number,
+    favoritesOnly: boolean,
+    regex = false
+  ) =>
+    tauriClient.searchPromptsPage(query, offset, limit, favoritesOnly, regex)
 };
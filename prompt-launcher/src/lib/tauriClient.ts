@@ -1,20 +1,122 @@
 import { invoke } from "@tauri-apps/api/core";
-import type { AppConfig, PromptEntry, RecentState } from "./types";
+import type {
+  AppConfig,
+  PromptEntry,
+  PromptRoot,
+  RecentState,
+  SavedSearch,
+  SearchHit,
+  SearchPage,
+  TagCompletion,
+  TagEditReport,
+  TagInfo,
+  TagPolicy,
+  TagRule,
+  TagRuleMatch,
+  TagStats,
+  TagSuggestion
+} from "./types";
 
 export const tauriClient = {
   getConfig: () => invoke<AppConfig>("get_config"),
   listPrompts: () => invoke<PromptEntry[]>("list_prompts"),
-  searchPrompts: (query: string, limit: number, favoritesOnly: boolean) =>
-    invoke<PromptEntry[]>("search_prompts", { query, limit, favoritesOnly }),
+  searchPrompts: (
+    query: string,
+    limit: number,
+    favoritesOnly: boolean,
+    regex = false
+  ) =>
+    invoke<PromptEntry[]>("search_prompts", {
+      query,
+      limit,
+      favoritesOnly,
+      regex
+    }),
+  searchPromptsPage: (
+    query: string,
+    offset: number,
+    limit: number,
+    favoritesOnly: boolean,
+    regex = false
+  ) =>
+    invoke<SearchPage>("search_prompts_page", {
+      query,
+      offset,
+      limit,
+      favoritesOnly,
+      regex
+    }),
+  searchPromptHits: (
+    query: string,
+    limit: number,
+    favoritesOnly: boolean,
+    regex = false
+  ) =>
+    invoke<SearchHit[]>("search_prompt_hits", {
+      query,
+      limit,
+      favoritesOnly,
+      regex
+    }),
+  getTagStats: (
+    query: string,
+    limit: number,
+    favoritesOnly = false,
+    regex = false
+  ) =>
+    invoke<TagStats>("get_tag_stats", { query, limit, favoritesOnly, regex }),
   setPromptsDir: (path: string) =>
     invoke<PromptEntry[]>("set_prompts_dir", { path }),
-  createPromptFile: (name: string) =>
-    invoke<string>("create_prompt_file", { name }),
+  setPromptRoots: (roots: PromptRoot[]) =>
+    invoke<PromptEntry[]>("set_prompt_roots", { roots }),
+  createPromptFile: (name: string, root: string | null = null) =>
+    invoke<string>("create_prompt_file", { name, root }),
   openPromptPath: (path: string) => invoke("open_prompt_path", { path }),
   deletePromptFiles: (paths: string[]) =>
     invoke<PromptEntry[]>("delete_prompt_files", { paths }),
   updatePromptTags: (paths: string[], add: string[], remove: string[]) =>
     invoke<PromptEntry[]>("update_prompt_tags", { paths, add, remove }),
+  completeTags: (input: string, limit: number) =>
+    invoke<TagCompletion[]>("complete_tags", { input, limit }),
+  suggestPromptTags: (path: string, limit: number) =>
+    invoke<TagSuggestion[]>("suggest_prompt_tags", { path, limit }),
+  setFolderTags: (folder: string, tags: string[]) =>
+    invoke<PromptEntry[]>("set_folder_tags", { folder, tags }),
+  listTagAliases: () => invoke<Record<string, string>>("list_tag_aliases"),
+  setTagAlias: (alias: string, canonical: string) =>
+    invoke<PromptEntry[]>("set_tag_alias", { alias, canonical }),
+  removeTagAlias: (alias: string) =>
+    invoke<PromptEntry[]>("remove_tag_alias", { alias }),
+  mergeTagAliases: () => invoke<PromptEntry[]>("merge_tag_aliases"),
+  getTagPolicy: () => invoke<TagPolicy>("get_tag_policy"),
+  setTagPolicy: (policy: TagPolicy) =>
+    invoke<PromptEntry[]>("set_tag_policy", { policy }),
+  listTagInfo: () => invoke<Record<string, TagInfo>>("list_tag_info"),
+  setTagInfo: (tag: string, info: TagInfo) =>
+    invoke<Record<string, TagInfo>>("set_tag_info", { tag, info }),
+  deleteTagInfo: (tag: string) =>
+    invoke<Record<string, TagInfo>>("delete_tag_info", { tag }),
+  getTagRules: (root: string) => invoke<TagRule[]>("get_tag_rules", { root }),
+  setTagRules: (root: string, rules: TagRule[]) =>
+    invoke<PromptEntry[]>("set_tag_rules", { root, rules }),
+  previewTagRule: (rule: TagRule) =>
+    invoke<TagRuleMatch[]>("preview_tag_rule", { rule }),
+  renameTag: (from: string, to: string, renameFiles: boolean, dryRun: boolean) =>
+    invoke<TagEditReport>("rename_tag", { from, to, renameFiles, dryRun }),
+  mergeTags: (
+    sources: string[],
+    target: string,
+    renameFiles: boolean,
+    dryRun: boolean
+  ) =>
+    invoke<TagEditReport>("merge_tags", {
+      sources,
+      target,
+      renameFiles,
+      dryRun
+    }),
+  deleteTag: (tag: string, renameFiles: boolean, dryRun: boolean) =>
+    invoke<TagEditReport>("delete_tag", { tag, renameFiles, dryRun }),
   setAutoPaste: (autoPaste: boolean) =>
     invoke("set_auto_paste", { autoPaste }),
   setAppendClipboard: (appendClipboard: boolean) =>
@@ -34,6 +136,31 @@ export const tauriClient = {
     invoke("set_preview_chars", { previewChars }),
   setShowShortcutsHint: (showShortcutsHint: boolean) =>
     invoke("set_show_shortcuts_hint", { showShortcutsHint }),
+  setFrecency: (weight: number, halfLifeHours: number) =>
+    invoke("set_frecency", { weight, halfLifeHours }),
+  setWatchQuietMs: (quietMs: number) =>
+    invoke("set_watch_quiet_ms", { quietMs }),
+  listSavedSearches: () => invoke<SavedSearch[]>("list_saved_searches"),
+  createSavedSearch: (
+    name: string,
+    query: string,
+    favoritesOnly: boolean,
+    hotkey: string | null,
+    regex = false
+  ) =>
+    invoke<SavedSearch>("create_saved_search", {
+      name,
+      query,
+      favoritesOnly,
+      hotkey,
+      regex
+    }),
+  updateSavedSearch: (search: SavedSearch) =>
+    invoke<SavedSearch>("update_saved_search", { search }),
+  deleteSavedSearch: (id: string) =>
+    invoke<SavedSearch[]>("delete_saved_search", { id }),
+  runSavedSearch: (id: string, limit: number) =>
+    invoke<PromptEntry[]>("run_saved_search", { id, limit }),
   clearRecent: () => invoke<RecentState>("clear_recent"),
   captureActiveWindow: () => invoke("capture_active_window"),
   focusLastWindow: (autoPaste: boolean) =>
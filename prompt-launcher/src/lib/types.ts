@@ -5,6 +5,35 @@ export type PromptEntry = {
   preview: string;
   tags: string[];
   path: string;
+  source: string;
+};
+
+export type TagCount = {
+  tag: string;
+  count: number;
+};
+
+export type SearchPage = {
+  items: PromptEntry[];
+  total: number;
+  offset: number;
+  tag_counts: TagCount[];
+};
+
+export type MatchField = "title" | "tags" | "content" | "body" | "path";
+
+/** Character offsets `[start, end)`. */
+export type HighlightRange = [number, number];
+
+export type SearchHit = {
+  prompt: PromptEntry;
+  score: number;
+  matched_field: MatchField | null;
+  highlights: {
+    title: HighlightRange[];
+    preview: HighlightRange[];
+    tags: HighlightRange[][];
+  };
 };
 
 export type AppConfig = {
@@ -21,9 +50,116 @@ export type AppConfig = {
   top_tags_limit: number;
   show_shortcuts_hint: boolean;
   preview_chars: number;
+  use_counts: Record<string, number>;
+  frecency_weight: number;
+  frecency_half_life_hours: number;
+  saved_searches: SavedSearch[];
+  prompt_roots: PromptRoot[];
+  watch_quiet_ms: number;
+};
+
+export type PromptRoot = {
+  path: string;
+  label: string;
+  enabled: boolean;
+};
+
+export type SavedSearch = {
+  id: string;
+  name: string;
+  query: string;
+  favorites_only: boolean;
+  hotkey: string | null;
+  regex: boolean;
+};
+
+export type TagCharClass =
+  | "ascii_alnum"
+  | "cjk"
+  | "kana"
+  | "hangul"
+  | "letter"
+  | "digit";
+
+export type TagPolicy = {
+  max_chars: number;
+  char_classes: TagCharClass[];
+  separators: string;
+  case_fold: boolean;
+};
+
+export type TagInfo = {
+  color: string | null;
+  description: string;
+  icon: string | null;
+  pinned: boolean;
+  sort_order: number;
+};
+
+export type TagRule = {
+  name: string;
+  tags: string[];
+  body_regex: string | null;
+  path_glob: string | null;
+  extension: string | null;
+  front_matter: { key: string; value: string | null } | null;
+};
+
+export type TagRuleMatch = {
+  path: string;
+  title: string;
+  added: string[];
+};
+
+export type TagCompletion = {
+  tag: string;
+  count: number;
+  usage: number;
+  alias: string | null;
+};
+
+export type TagSuggestion = {
+  tag: string;
+  score: number;
+  keyword_score: number;
+  similar_score: number;
+};
+
+export type TagStat = {
+  tag: string;
+  count: number;
+  usage: number;
+};
+
+export type TagPair = {
+  a: string;
+  b: string;
+  count: number;
+};
+
+export type TagStats = {
+  prompts: number;
+  tags: TagStat[];
+  pairs: TagPair[];
+};
+
+export type FileRename = {
+  from: string;
+  to: string;
+};
+
+export type TagEditReport = {
+  dry_run: boolean;
+  prompts: string[];
+  renames: FileRename[];
 };
 
 export type RecentState = {
   recent_ids: string[];
   recent_meta: Record<string, number>;
 };
+
+export type PromptsDelta = {
+  upserted: PromptEntry[];
+  removed: string[];
+};